@@ -1,11 +1,11 @@
-use hraefnstead_lib::state::State;
+use hraefnstead_lib::{state::State, world::World};
 use leptos::html::Input;
 use leptos::prelude::*;
 use leptos::task::spawn_local;
 use log::info;
 
 #[component]
-pub fn FileUpload(state: RwSignal<State>) -> impl IntoView {
+pub fn FileUpload(world: RwSignal<World>, state: RwSignal<State>) -> impl IntoView {
     let file_selector: NodeRef<Input> = NodeRef::new();
 
     let load_file = move |_| {
@@ -19,9 +19,9 @@ pub fn FileUpload(state: RwSignal<State>) -> impl IntoView {
                             wasm_bindgen_futures::JsFuture::from(file.text()).await
                         {
                             if let Some(file_content) = file_content.as_string() {
-                                let new_state = serde_json::from_str(&file_content);
-                                if let Ok(new_state) = new_state {
-                                    state.set(new_state);
+                                match State::load(world.get_untracked(), &file_content) {
+                                    Ok(new_state) => state.set(new_state),
+                                    Err(err) => info!("failed to load game {file_name}: {err}"),
                                 }
                             }
                         }
@@ -39,12 +39,50 @@ pub fn FileUpload(state: RwSignal<State>) -> impl IntoView {
     }
 }
 
+#[component]
+pub fn WorldUpload(world: RwSignal<World>, state: RwSignal<State>) -> impl IntoView {
+    let file_selector: NodeRef<Input> = NodeRef::new();
+
+    let load_world = move |_| {
+        if let Some(files_input) = file_selector.get_untracked() {
+            if let Some(files) = files_input.files() {
+                if let Some(file) = files.get(0) {
+                    let file_name = file.name();
+                    info!("loading world {file_name}");
+                    spawn_local(async move {
+                        if let Ok(file_content) =
+                            wasm_bindgen_futures::JsFuture::from(file.text()).await
+                        {
+                            if let Some(file_content) = file_content.as_string() {
+                                match file_content.parse::<World>() {
+                                    Ok(new_world) => {
+                                        state.set(State::from_world(new_world.clone()));
+                                        world.set(new_world);
+                                    }
+                                    Err(err) => info!("failed to load world {file_name}: {err}"),
+                                }
+                            }
+                        }
+                    });
+                }
+            }
+        }
+    };
+
+    view! {
+         <span>
+             <button class="custom-button" on:click=load_world >Load World</button>
+             <input type="file" name="world_to_upload" id="world_to_upload" class="file-input" node_ref=file_selector />
+         </span>
+    }
+}
+
 #[component]
 pub fn FileDownload(state: RwSignal<State>) -> impl IntoView {
     let download_file = move || {
         format!(
             "data:text/plain;charset=utf-8,{}",
-            serde_json::to_string(&(state.get())).unwrap()
+            state.with(|s| s.save(now())).unwrap()
         )
     };
 
@@ -52,3 +90,17 @@ pub fn FileDownload(state: RwSignal<State>) -> impl IntoView {
         <a class="button" href=download_file download="adventure_state.json">Save Game</a>
     }
 }
+
+/// Seconds since the unix epoch, `SystemTime` is not available in the browser.
+fn now() -> u64 {
+    cfg_if::cfg_if! {
+        if #[cfg(target_arch = "wasm32")] {
+            (js_sys::Date::now() / 1000.0) as u64
+        } else {
+            std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .map(|duration| duration.as_secs())
+                .unwrap_or_default()
+        }
+    }
+}
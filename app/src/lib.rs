@@ -1,4 +1,4 @@
-use hraefnstead_lib::{command::Command, parser::parse, state::State, victory::Victory, GAME_OVER};
+use hraefnstead_lib::{state::State, victory::Victory, world::World, GAME_OVER};
 use leptos::{html::Textarea, prelude::*};
 use leptos_meta::{provide_meta_context, MetaTags, Stylesheet, Title};
 use leptos_router::{
@@ -8,7 +8,7 @@ use leptos_router::{
 
 mod files;
 
-use files::{FileDownload, FileUpload};
+use files::{FileDownload, FileUpload, WorldUpload};
 
 pub fn shell(options: LeptosOptions) -> impl IntoView {
     view! {
@@ -53,17 +53,17 @@ pub fn App() -> impl IntoView {
 fn process_victory(state: &mut State, victory: &Victory) {
     match victory {
         Victory::Won => {
-            state.log("\n!!!Congratulations You won the Game!!!");
+            state.log(&format!("\n{}", state.message("won", &[])));
         }
         Victory::GameOver => {
-            state.log("\nYou are dead.\nIf you want to try again, press the restart button or load a previously saved state.");
+            state.log(&format!("\n{}", state.message("dead_restart", &[])));
             state.set_location(GAME_OVER);
         }
         Victory::Load(_) | Victory::Save(_) => {
-            state.log("\nPlease use the button below to save or load game states.")
+            state.log(&format!("\n{}", state.message("use_buttons", &[])))
         }
         Victory::Quit => {
-            state.log("\nIf you want to quit, just close the window.");
+            state.log(&format!("\n{}", state.message("close_window", &[])));
         }
         Victory::None => {}
     }
@@ -75,9 +75,13 @@ fn HomePage() -> impl IntoView {
     use leptos::ev::SubmitEvent;
     use leptos::html::Input;
 
-    let state = RwSignal::new(State::new());
+    let world = RwSignal::new(World::default());
+    let state = RwSignal::new(State::from_world(world.get_untracked()));
     // Creates a reactive value to update the button
-    let output = RwSignal::new("Welcome to the dungeons of hraefnstead!\nType:'help' to briefly view possible actions.\nTyping said actions prior to 'help' will reveal more about their quality.\n".to_string());
+    let output = RwSignal::new(format!(
+        "{}\n",
+        state.get_untracked().message("welcome", &[])
+    ));
     let command_input: NodeRef<Input> = NodeRef::new();
     let output_area: NodeRef<Textarea> = NodeRef::new();
 
@@ -85,24 +89,8 @@ fn HomePage() -> impl IntoView {
         ev.prevent_default();
         let value = command_input.get().expect("<command> to exist").value();
 
-        let mut new_command = Command::None;
-        state.update(|s| {
-            new_command = parse(&value, s);
-        });
         let mut command_result = Victory::None;
-        let mut command_stack = None;
-        state.update(|s| command_stack = s.special_event_triggered(&new_command));
-        if let Some(command_stack) = command_stack {
-            for new_command in command_stack {
-                state.update(|s| {
-                    command_result = new_command.execute(s);
-                });
-            }
-        } else {
-            state.update(|s| {
-                command_result = new_command.execute(s);
-            });
-        }
+        state.update(|s| command_result = s.play(&value));
         state.update(|s| process_victory(s, &command_result));
         let mut log = String::new();
         state.update(|s| log = s.get_log());
@@ -131,11 +119,12 @@ fn HomePage() -> impl IntoView {
             </div>
             <div class="buttons">
             <button on:click=move |_|  {
-                state.set(State::new());
+                state.set(State::from_world(world.get_untracked()));
                 output.set(String::new()); }
             >"Restart game"</button>
-            <FileUpload state />
+            <FileUpload world state />
             <FileDownload state />
+            <WorldUpload world state />
             </div>
     }
 }
@@ -1,6 +1,9 @@
-use clap::Parser;
-use hraefnstead_lib::{parser::parse, state::State, victory::Victory, GAME_OVER};
+use clap::{Parser, Subcommand};
+use hraefnstead_lib::{
+    dsl, locale::Locale, parser::normalize, state::State, victory::Victory, world::World, GAME_OVER,
+};
 use std::io::{self, Write};
+use std::time::{SystemTime, UNIX_EPOCH};
 
 #[derive(Parser)]
 #[command(version, about, long_about = None)]
@@ -9,6 +12,14 @@ struct Cli {
     #[arg(short, long, value_name = "FILE")]
     game: Option<String>,
 
+    /// Sets the world definition file, default is the bundled dungeon
+    #[arg(short, long, value_name = "FILE")]
+    world: Option<String>,
+
+    /// Sets the language of the game, 'en' or 'de', default is the world's
+    #[arg(short, long, value_name = "LANGUAGE")]
+    locale: Option<Locale>,
+
     /// Turn debugging information on
     #[arg(short, long, action = clap::ArgAction::Count)]
     debug: u8,
@@ -16,93 +27,133 @@ struct Cli {
     /// Test mode, suppress loading default game settings
     #[arg(short, long)]
     test: bool,
+
+    #[command(subcommand)]
+    command: Option<Commands>,
+}
+
+#[derive(Subcommand)]
+enum Commands {
+    /// Check a world file for dangling references and other mistakes
+    Validate {
+        /// The world definition file to check
+        world: String,
+    },
+    /// Compile a world written in the world language to a JSON world file
+    Compile {
+        /// The world language source file
+        source: String,
+        /// The JSON file to write, default is printing it
+        #[arg(short, long, value_name = "FILE")]
+        output: Option<String>,
+    },
+    /// Print the room graph of a world in Graphviz DOT format
+    Graph {
+        /// The world definition file, default is the bundled dungeon
+        world: Option<String>,
+        /// Also show the events, what they activate and what they depend on
+        #[arg(short, long)]
+        events: bool,
+    },
 }
 
 fn main() {
     let cli = Cli::parse();
 
-    println!("Welcome to the dungeons of hraefnstead!\nType:'help' to briefly view possible actions.\nTyping said actions prior to 'help' will reveal more about their quality. ");
+    match &cli.command {
+        Some(Commands::Validate { world }) => {
+            validate_world(world);
+            return;
+        }
+        Some(Commands::Compile { source, output }) => {
+            compile_world(source, output.as_deref());
+            return;
+        }
+        Some(Commands::Graph { world, events }) => {
+            graph_world(world.as_deref(), *events);
+            return;
+        }
+        None => {}
+    }
+
+    let locale = cli.locale;
+
+    let world = if let Some(world_file) = cli.world {
+        match World::from_file(&world_file) {
+            Ok(world) => world,
+            Err(err) => {
+                println!("Failed to load world '{world_file}': {err}");
+                return;
+            }
+        }
+    } else {
+        World::default()
+    };
 
     let mut game_file = SAVE_FILE.to_string();
 
     let mut state = if cli.test {
-        State::new()
+        State::from_world(world.clone())
     } else {
         if let Some(file) = cli.game {
             game_file = file;
         }
-        if let Some(new_state) = load_game(&game_file) {
+        if let Some(new_state) = load_game(&game_file, &world) {
             new_state
         } else {
             println!("Start new game instead.");
-            State::new()
+            State::from_world(world.clone())
         }
     };
+    if let Some(locale) = locale {
+        state.set_locale(locale);
+    }
+    println!("{}", state.message("welcome", &[]));
 
-    let mut victory = Victory::None;
     loop {
-        let mut input = String::new();
         print!("\n---> ");
-        io::stdout().flush().expect("Failed to flush");
-        io::stdin()
-            .read_line(&mut input)
-            .expect("Failed to read line");
-        input = input.to_lowercase();
-
-        let command = parse(&input, &mut state);
-        if let Some(command_stack) = state.special_event_triggered(&command) {
-            for command in command_stack {
-                victory = command.execute(&mut state);
-            }
-        } else {
-            victory = command.execute(&mut state);
-        }
+        let Some(input) = read_input() else {
+            return;
+        };
+
+        let victory = state.play(&input);
 
         println!("{}", state.get_log());
         match victory {
             Victory::GameOver => {
                 loop {
-                    state.log("\nYou are dead.\nWould you like to try again? (yes/no): ");
+                    state.log(&format!("\n{}", state.message("dead_try_again", &[])));
                     state.set_location(GAME_OVER);
                     println!("{}", state.get_log());
-                    let mut input = String::new();
-                    io::stdout().flush().expect("Failed to flush");
-                    io::stdin()
-                        .read_line(&mut input)
-                        .expect("Failed to read line");
-                    input = input.to_lowercase();
-                    let mut tokens = input.split_whitespace();
-                    let answer = tokens.next().unwrap();
-                    match answer {
-                        "yes" => {
-                            load_game(&game_file);
+                    let Some(input) = read_input() else {
+                        return;
+                    };
+                    match answer(&state, &input) {
+                        Some(true) => {
+                            state = load_game(&game_file, &world)
+                                .unwrap_or_else(|| new_game(&world, locale));
                             break;
                         }
-                        "no" => return,
-                        _ => {}
+                        Some(false) => return,
+                        None => {}
                     };
                 }
                 //state.log(&msg);
             }
             Victory::Won => {
                 loop {
-                    state.log("\n!!!Congratulations You won the Game!!!\nWould you like to start a new Game? (yes/no): ");
+                    state.log(&format!("\n{}", state.message("won_new_game", &[])));
                     println!("{}", state.get_log());
-                    let mut input = String::new();
-                    io::stdout().flush().expect("Failed to flush");
-                    io::stdin()
-                        .read_line(&mut input)
-                        .expect("Failed to read line");
-                    input = input.to_lowercase();
-                    let mut tokens = input.split_whitespace();
-                    let answer = tokens.next().unwrap();
-                    match answer {
-                        "yes" => {
-                            state = State::new();
+                    let Some(input) = read_input() else {
+                        return;
+                    };
+                    match answer(&state, &input) {
+                        Some(true) => {
+                            state = new_game(&world, locale);
                             break;
                         }
-                        "no" => return,
-                        _ => {}
+                        Some(false) => return,
+                        None => {}
                     };
                 }
                 //state.log(&msg);
@@ -113,7 +164,7 @@ fn main() {
                 if !name.is_empty() {
                     game_file = name.to_string();
                 }
-                if let Some(new_state) = load_game(&game_file) {
+                if let Some(new_state) = load_game(&game_file, &world) {
                     state = new_state;
                 }
             }
@@ -129,13 +180,45 @@ fn main() {
 
 pub const SAVE_FILE: &str = "adventure_state.json";
 
-pub fn load_game(name: &str) -> Option<State> {
+/// A fresh game in the world, in the language picked on the command line.
+fn new_game(world: &World, locale: Option<Locale>) -> State {
+    let mut state = State::from_world(world.clone());
+    if let Some(locale) = locale {
+        state.set_locale(locale);
+    }
+    state
+}
+
+/// Whether the player answered yes or no in the language of the game.
+fn answer(state: &State, input: &str) -> Option<bool> {
+    let answer = normalize(input, state.get_vocabulary());
+    if answer == state.message("yes", &[]) {
+        Some(true)
+    } else if answer == state.message("no", &[]) {
+        Some(false)
+    } else {
+        None
+    }
+}
+
+/// Read a line from the player, `None` at the end of the input.
+fn read_input() -> Option<String> {
+    let mut input = String::new();
+    io::stdout().flush().expect("Failed to flush");
+    let read = io::stdin()
+        .read_line(&mut input)
+        .expect("Failed to read line");
+    (read > 0).then_some(input)
+}
+
+pub fn load_game(name: &str, world: &World) -> Option<State> {
     if let Ok(state_json) = std::fs::read_to_string(name) {
-        if let Ok(new_state) = serde_json::from_str(&state_json) {
-            Some(new_state)
-        } else {
-            println!("Game file seems to be corrupt!");
-            None
+        match State::load(world.clone(), &state_json) {
+            Ok(new_state) => Some(new_state),
+            Err(err) => {
+                println!("{err}");
+                None
+            }
         }
     } else {
         println!("Could not find file!");
@@ -144,7 +227,11 @@ pub fn load_game(name: &str) -> Option<State> {
 }
 
 pub fn save_game(name: &str, state: &State) {
-    if let Ok(state_json) = serde_json::to_string(&state) {
+    let timestamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_secs())
+        .unwrap_or_default();
+    if let Ok(state_json) = state.save(timestamp) {
         if std::fs::write(name, state_json).is_err() {
             println!("Failed to write file.")
         }
@@ -152,3 +239,70 @@ pub fn save_game(name: &str, state: &State) {
         println!("Failed to serialize game state");
     }
 }
+
+pub fn validate_world(name: &str) {
+    let world = match World::from_file(name) {
+        Ok(world) => world,
+        Err(err) => {
+            println!("Failed to load world '{name}': {err}");
+            std::process::exit(1);
+        }
+    };
+    let diagnostics = world.validate();
+    for diagnostic in diagnostics.iter() {
+        println!("{diagnostic}");
+    }
+    let errors = diagnostics.iter().filter(|d| d.is_error()).count();
+    let warnings = diagnostics.len() - errors;
+    println!("{name}: {errors} error(s), {warnings} warning(s)");
+    if errors > 0 {
+        std::process::exit(1);
+    }
+}
+
+pub fn compile_world(name: &str, output: Option<&str>) {
+    let text = match std::fs::read_to_string(name) {
+        Ok(text) => text,
+        Err(err) => {
+            println!("Failed to read '{name}': {err}");
+            std::process::exit(1);
+        }
+    };
+    let source = match dsl::compile(&text) {
+        Ok(source) => source,
+        Err(errors) => {
+            for error in errors.iter() {
+                println!("{name}: {error}");
+            }
+            std::process::exit(1);
+        }
+    };
+    if let Err(err) = source.resolve() {
+        println!("{name}: {err}");
+        std::process::exit(1);
+    }
+    let json = serde_json::to_string_pretty(&source).expect("world sources always serialize");
+    match output {
+        Some(output) => {
+            if let Err(err) = std::fs::write(output, json) {
+                println!("Failed to write '{output}': {err}");
+                std::process::exit(1);
+            }
+        }
+        None => println!("{json}"),
+    }
+}
+
+pub fn graph_world(name: Option<&str>, events: bool) {
+    let world = match name {
+        Some(name) => match World::from_file(name) {
+            Ok(world) => world,
+            Err(err) => {
+                println!("Failed to load world '{name}': {err}");
+                std::process::exit(1);
+            }
+        },
+        None => World::default(),
+    };
+    print!("{}", world.to_dot(events));
+}
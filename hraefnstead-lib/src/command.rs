@@ -1,4 +1,7 @@
 use crate::direction::Direction;
+use crate::door::DoorState;
+use crate::id::{ActorId, DoorId, EntityId, EventId, Object, RoomId};
+use crate::map::render_map;
 //use std::io::{self, Write};
 
 use crate::state::State;
@@ -12,56 +15,165 @@ pub enum Command {
     Look,
     Take(String),
     Drop(String),
+    /// Take everything in the room except the named objects.
+    TakeAll(Vec<String>),
+    /// Drop everything carried except the named objects.
+    DropAll(Vec<String>),
     Examine(String),
     Save(String),
     Load(String),
     Quit,
     Inventory,
-    AddItemToRoom(usize),
+    AddItemToRoom(EntityId),
     None,
-    DeActivateEvent(usize),
-    ActivateEvent(usize),
+    DeActivateEvent(EventId),
+    ActivateEvent(EventId),
     Use(String),
-    RemoveActor(usize),
+    RemoveActor(ActorId),
     //Empty,
     Eat(String),
-    Consume(usize),
-    AddExit(Direction, usize), // Denial,
+    Consume(EntityId),
+    AddExit(Direction, RoomId), // Denial,
     Craft(String),
     CraftHelp,
     Attack(String),
     GameOver,
     Won,
     Help(String),
+    /// Run the script of an event.
+    RunScript(EventId),
+    Map,
+    /// Repeat the last command.
+    Again,
+    /// List the inputs of this session.
+    History,
+    /// Use the first object on the second one.
+    UseOn(String, String),
+    /// Give the first object to the second one.
+    Give(String, String),
+    /// Put the first object into the second one.
+    PutIn(String, String),
+    /// Attack the first object with the second one.
+    AttackWith(String, String),
+    Open(String),
+    Close(String),
+    /// Lock a door with the key the player carries.
+    Lock(String),
+    /// Unlock a door with the key the player carries.
+    Unlock(String),
+    /// Lock the door with the second object.
+    LockWith(String, String),
+    /// Unlock the door with the second object.
+    UnlockWith(String, String),
     // Denial,
     //TriggerDialog,
     // StateOfDialog(usize),
 }
 
 impl Command {
+    /// What the player named as the object of the command, if it has one.
+    pub fn object(&self) -> Option<&str> {
+        match self {
+            Command::Take(thing)
+            | Command::Drop(thing)
+            | Command::Examine(thing)
+            | Command::Use(thing)
+            | Command::Eat(thing)
+            | Command::Craft(thing)
+            | Command::Attack(thing)
+            | Command::Open(thing)
+            | Command::Close(thing)
+            | Command::Lock(thing)
+            | Command::Unlock(thing)
+            | Command::UseOn(thing, _)
+            | Command::Give(thing, _)
+            | Command::PutIn(thing, _)
+            | Command::AttackWith(thing, _)
+            | Command::LockWith(thing, _)
+            | Command::UnlockWith(thing, _) => Some(thing),
+            _ => None,
+        }
+    }
+
+    /// The command with its objects replaced by `f` of them.
+    pub fn map_objects(&self, mut f: impl FnMut(&str) -> String) -> Command {
+        match self {
+            Command::Take(thing) => Command::Take(f(thing)),
+            Command::Drop(thing) => Command::Drop(f(thing)),
+            Command::Examine(thing) => Command::Examine(f(thing)),
+            Command::Use(thing) => Command::Use(f(thing)),
+            Command::Eat(thing) => Command::Eat(f(thing)),
+            Command::Craft(thing) => Command::Craft(f(thing)),
+            Command::Attack(thing) => Command::Attack(f(thing)),
+            Command::Open(thing) => Command::Open(f(thing)),
+            Command::Close(thing) => Command::Close(f(thing)),
+            Command::Lock(thing) => Command::Lock(f(thing)),
+            Command::Unlock(thing) => Command::Unlock(f(thing)),
+            Command::UseOn(thing, other) => Command::UseOn(f(thing), f(other)),
+            Command::Give(thing, other) => Command::Give(f(thing), f(other)),
+            Command::PutIn(thing, other) => Command::PutIn(f(thing), f(other)),
+            Command::AttackWith(thing, other) => Command::AttackWith(f(thing), f(other)),
+            Command::LockWith(thing, other) => Command::LockWith(f(thing), f(other)),
+            Command::UnlockWith(thing, other) => Command::UnlockWith(f(thing), f(other)),
+            command => command.clone(),
+        }
+    }
+
+    /// The second object of a command with two objects.
+    pub fn indirect_object(&self) -> Option<&str> {
+        match self {
+            Command::UseOn(_, other)
+            | Command::Give(_, other)
+            | Command::PutIn(_, other)
+            | Command::AttackWith(_, other)
+            | Command::LockWith(_, other)
+            | Command::UnlockWith(_, other) => Some(other),
+            _ => None,
+        }
+    }
+
     pub fn execute(&self, state: &mut State) -> Victory {
         match self {
             Command::Quit => {
+                state.log(&state.message("goodbye", &[]));
                 return Victory::Quit;
             }
-            Command::Save(name) => return Victory::Save(name.clone()),
+            Command::Save(name) => {
+                state.log(&state.message("saving", &[]));
+                return Victory::Save(name.clone());
+            }
             Command::Load(name) => {
+                state.log(&state.message("loading", &[]));
                 return Victory::Load(name.clone());
             }
             Command::Look => {
-                let room = state.get_room();
-                let mut msg = room.get_description().to_string();
-                let exits = room.get_exits();
+                let mut msg = state.get_room_description().to_string();
+                let exits = state.get_exits();
                 if exits.is_empty() {
-                    msg = format!("{msg}\nThere seems to be no exit.\n");
+                    msg = format!("{msg}\n{}\n", state.message("no_exit", &[]));
                 } else {
-                    msg = format!("{msg}\nExits:");
-                    for (dir, _) in exits.iter() {
-                        msg = format!("{msg}{dir} ");
+                    msg = format!("{msg}\n{}", state.message("exits", &[]));
+                    let mut exits: Vec<_> = exits.keys().collect();
+                    exits.sort();
+                    for dir in exits {
+                        msg = format!("{msg}{} ", state.direction_name(dir));
                     }
                     msg = format!("{msg}\n");
                 }
-                let actors = room.get_actors();
+                for (door_id, dir) in state.get_room_doors() {
+                    let id = match state.door_state(door_id) {
+                        DoorState::Open => continue,
+                        DoorState::Closed => "look_door_closed",
+                        DoorState::Locked => "look_door_locked",
+                    };
+                    let door = state.object_name(Object::Door(door_id));
+                    let direction = state.direction_name(&dir);
+                    msg = format!(
+                        "{msg}{}\n",
+                        state.message(id, &[("door", &door), ("direction", &direction)])
+                    );
+                }
+                let actors = state.get_room_actors();
                 if !actors.is_empty() {
                     for actor in actors.iter() {
                         if let Some(actor) = state.get_actor(*actor) {
@@ -70,11 +182,11 @@ impl Command {
                     }
                     msg = format!("{msg}\n");
                 }
-                let entities = room.get_entities();
+                let entities = state.get_room_entities();
                 if entities.is_empty() {
-                    msg = format!("{msg}\nThere is nothing here.")
+                    msg = format!("{msg}\n{}", state.message("nothing_here", &[]))
                 } else {
-                    msg = format!("{msg}\nYou see:");
+                    msg = format!("{msg}\n{}", state.message("you_see", &[]));
                     for e in entities.iter() {
                         if let Some(entity) = state.get_entity(*e) {
                             msg = format!("{msg}\n{}", entity.get_name())
@@ -84,40 +196,69 @@ impl Command {
                 state.log(&msg);
             }
             Command::Move(dir) => {
-                if let Some(new_room) = state.get_exit(dir.clone()) {
+                let blocked = state.door_at(dir).and_then(|door_id| {
+                    let id = match state.door_state(door_id) {
+                        DoorState::Open => return None,
+                        DoorState::Closed => "door_closed",
+                        DoorState::Locked => "door_locked",
+                    };
+                    let door = state.object_name(Object::Door(door_id));
+                    Some(state.message(id, &[("door", &door)]))
+                });
+                if let Some(msg) = blocked {
+                    state.log(&format!("\n{msg}"));
+                } else if let Some(new_room) = state.get_exit(dir.clone()) {
                     state.set_location(new_room);
                     Command::Look.execute(state);
                 } else {
-                    let msg = "\nYou can't go that way.".to_string();
+                    let msg = format!("\n{}", state.message("cant_go", &[]));
                     state.log(&msg);
                 }
             }
             Command::Take(thing) => {
                 let msg = if state.take_entity_from_room(thing) {
-                    "\nTaken.".to_string()
+                    state.message("taken", &[])
                 } else {
-                    format!("\nThere is no {thing} here.")
+                    state.message("not_here", &[("thing", thing)])
                 };
+                let msg = format!("\n{msg}");
                 state.log(&msg);
             }
             Command::Drop(thing) => {
                 let msg;
+                let locale = state.locale();
                 if let Some((entity_id, entity)) = state.get_from_inventory(thing) {
-                    msg = format!("\nYou drop the {}", entity.get_name());
-                    let room = state.get_room_mut();
-                    room.add_entity(entity_id);
+                    msg = locale.format("you_drop", &[("thing", entity.get_name())]);
+                    state.add_entity_to_room(entity_id);
                 } else {
-                    msg = format!("\nYou don't have a {thing} to drop.");
+                    msg = state.message("nothing_to_drop", &[("thing", thing)]);
                 }
+                let msg = format!("\n{msg}");
                 state.log(&msg);
             }
+            Command::TakeAll(except) => {
+                let entities = state.room_entities_except(except);
+                if entities.is_empty() {
+                    state.log(&format!("\n{}", state.message("nothing_to_take", &[])));
+                }
+                let result = state.message("item_taken", &[]);
+                return each_entity(state, entities, Command::Take, &result);
+            }
+            Command::DropAll(except) => {
+                let entities = state.inventory_except(except);
+                if entities.is_empty() {
+                    state.log(&format!("\n{}", state.message("empty_handed", &[])));
+                }
+                let result = state.message("item_dropped", &[]);
+                return each_entity(state, entities, Command::Drop, &result);
+            }
             Command::Inventory => {
                 let mut msg;
                 let inventory = state.get_inventory();
                 if inventory.is_empty() {
-                    msg = "\nYou are empty handed.".to_string();
+                    msg = format!("\n{}", state.message("empty_handed", &[]));
                 } else {
-                    msg = "You have:".to_string();
+                    msg = state.message("you_have", &[]);
                     for entity_id in inventory.iter() {
                         if let Some(entity) = state.get_entity(*entity_id) {
                             msg = format!("{msg}\n{}", entity.name);
@@ -127,7 +268,7 @@ impl Command {
                 state.log(&msg)
             }
             Command::AddItemToRoom(entity_id) => {
-                state.get_room_mut().add_entity(*entity_id);
+                state.add_entity_to_room(*entity_id);
             }
             Command::DeActivateEvent(event_id) => state.de_activate_event(event_id),
             Command::ActivateEvent(event_id) => state.activate_event(event_id),
@@ -138,7 +279,7 @@ impl Command {
                         msg = format!("{msg}\n{}", entity.description);
                     }
                 } else {
-                    msg = format!("{msg}\nYou need to have item in inventory!");
+                    msg = format!("{msg}\n{}", state.message("not_in_inventory", &[]));
                 }
                 state.log(&msg)
             }
@@ -147,7 +288,7 @@ impl Command {
                 if let Some(id) = state.find_inventory(thing) {
                     state.consume_from_inventory(&id);
                 } else {
-                    msg = format!("{msg}\nYou need to have item in inventory!")
+                    msg = format!("{msg}\n{}", state.message("not_in_inventory", &[]))
                 }
                 state.log(&msg)
             }
@@ -164,33 +305,71 @@ impl Command {
                 }
             }
             Command::CraftHelp => state.craft_help(),
-            Command::AddExit(direction, room_number) => state
-                .get_room_mut()
-                .add_exit(direction.clone(), *room_number),
+            Command::AddExit(direction, room_number) => {
+                state.add_exit(direction.clone(), *room_number)
+            }
             Command::RemoveActor(actor_id) => {
-                state.get_room_mut().remove_actor(*actor_id);
+                state.remove_actor_from_room(*actor_id);
             }
             Command::GameOver => {
                 return Victory::GameOver;
             }
             Command::Won => return Victory::Won,
-            Command::Help(command) => {
-                let mut tokens = command.split_whitespace();
-                let answer = tokens.next().unwrap();
-                let msg = match answer{
-                    "look"=> "With look you get a brief description of your surroundings",
-                    "save"=> "Saves your game for you",
-                    "load"=> "Loads a prior saved game file",
-                    "examine"=> "Gives you a detailed description of specified Item/Object. Can also be applied on items in your inventory",
-                    "inventory"=> "Shows all items you are currently carrying with you",
-                    "go"=> "With go you can navigate into any direction you specify(north/south/east/west)",
-                    "use"=> "With use you can perform specific actions that require a specific item. Make sure to specify said items when using 'use'",
-                    "attack"=> "Doesn't the name speak for itself? Just keep in mind messing with the wrong people WILL get you in trouble",
-                    "craft"=> "With craft you consume item(s) to create new ones, that are oftentimes from much higher quality and value than there components",
-                    "Default"=> "look\nquit\nsave\ngo\ndrop\ninventory\nexamine\nuse\nattack\ncraft",
-                    _=> ""
+            Command::UseOn(thing, _) | Command::Give(thing, _) | Command::PutIn(thing, _) => {
+                let msg = if state.find_inventory(thing).is_some() {
+                    state.message("nothing_happens", &[])
+                } else {
+                    state.message("dont_have", &[("thing", thing)])
+                };
+                let msg = format!("\n{msg}");
+                state.log(&msg);
+            }
+            Command::AttackWith(_, weapon) => {
+                let msg = if state.find_inventory(weapon).is_some() {
+                    state.message("bad_idea", &[])
+                } else {
+                    state.message("dont_have", &[("thing", weapon)])
+                };
+                let msg = format!("\n{msg}");
+                state.log(&msg);
+            }
+            Command::Open(thing) => open_door(state, thing, true),
+            Command::Close(thing) => open_door(state, thing, false),
+            Command::Lock(thing) => lock_door(state, thing, None, true),
+            Command::Unlock(thing) => lock_door(state, thing, None, false),
+            Command::LockWith(thing, key) => lock_door(state, thing, Some(key), true),
+            Command::UnlockWith(thing, key) => lock_door(state, thing, Some(key), false),
+            Command::Map => {
+                let map = render_map(state);
+                state.log(&map);
+            }
+            Command::Again => {
+                let Some(entry) = state.get_history().last().cloned() else {
+                    state.log(&format!("\n{}", state.message("nothing_to_repeat", &[])));
+                    return Victory::None;
                 };
-                state.log(msg);
+                return state.run(&entry.command);
+            }
+            Command::History => {
+                let history = state.get_history();
+                let msg = if history.is_empty() {
+                    format!("\n{}", state.message("no_history", &[]))
+                } else {
+                    history
+                        .iter()
+                        .enumerate()
+                        .map(|(n, entry)| format!("\n{}. {}", n + 1, entry.input))
+                        .collect()
+                };
+                state.log(&msg);
+            }
+            Command::RunScript(event_id) => {
+                let script = state.get_world().events[event_id.0].script.clone();
+                return script.run(state);
+            }
+            Command::Help(topic) => {
+                let msg = state.get_vocabulary().help(topic);
+                state.log(&msg);
             }
 
             _ => {}
@@ -199,3 +378,116 @@ impl Command {
         Victory::None
     }
 }
+
+/// The door the phrase names in the current room, or why there is none.
+fn door(state: &State, thing: &str) -> Result<(DoorId, String), String> {
+    match state.find_door(thing) {
+        Some(door_id) => Ok((door_id, state.object_name(Object::Door(door_id)))),
+        None if state.find_object(thing).is_some() => {
+            Err(state.message("not_a_door", &[("thing", thing)]))
+        }
+        None => Err(state.message("not_here", &[("thing", thing)])),
+    }
+}
+
+/// Open or close a door.
+fn open_door(state: &mut State, thing: &str, open: bool) {
+    let msg = match door(state, thing) {
+        Err(msg) => msg,
+        Ok((door_id, name)) => {
+            let args = [("door", name.as_str())];
+            match (state.door_state(door_id), open) {
+                (DoorState::Open, true) => state.message("already_open", &args),
+                (DoorState::Closed, false) => state.message("already_closed", &args),
+                (DoorState::Locked, true) => state.message("door_locked", &args),
+                (DoorState::Locked, false) => state.message("already_closed", &args),
+                (DoorState::Closed, true) => {
+                    state.set_door_state(door_id, DoorState::Open);
+                    state.message("you_open", &args)
+                }
+                (DoorState::Open, false) => {
+                    state.set_door_state(door_id, DoorState::Closed);
+                    state.message("you_close", &args)
+                }
+            }
+        }
+    };
+    state.log(&format!("\n{msg}"));
+}
+
+/// Lock or unlock a door with the named key, or the one the player carries.
+fn lock_door(state: &mut State, thing: &str, key: Option<&str>, lock: bool) {
+    let msg = match door(state, thing) {
+        Err(msg) => msg,
+        Ok((door_id, name)) => {
+            let args = [("door", name.as_str())];
+            let door_key = state.get_door(door_id).and_then(|door| door.key);
+            let carried = match key {
+                Some(key) => state.find_inventory(key).ok_or_else(|| key.to_string()),
+                None => door_key
+                    .filter(|id| state.get_inventory().contains(id))
+                    .ok_or_else(String::new),
+            };
+            match (state.door_state(door_id), lock) {
+                _ if door_key.is_none() => state.message("no_lock", &args),
+                (DoorState::Open, true) => state.message("close_first", &args),
+                (DoorState::Locked, true) => state.message("already_locked", &args),
+                (DoorState::Open | DoorState::Closed, false) => state.message("not_locked", &args),
+                (door_state, _) => match carried {
+                    Err(key) if key.is_empty() => state.message("no_key", &args),
+                    Err(key) => state.message("dont_have", &[("thing", &key)]),
+                    Ok(id) if Some(id) != door_key => {
+                        let key = state.object_name(Object::Entity(id));
+                        state.message("wrong_key", &[("thing", &key)])
+                    }
+                    Ok(_) if door_state == DoorState::Closed => {
+                        state.set_door_state(door_id, DoorState::Locked);
+                        state.message("you_lock", &args)
+                    }
+                    Ok(_) => {
+                        state.set_door_state(door_id, DoorState::Closed);
+                        state.message("you_unlock", &args)
+                    }
+                },
+            }
+        }
+    };
+    state.log(&format!("\n{msg}"));
+}
+
+/// Take or drop each of the entities, with one line of result per entity.
+/// Events triggered by taking or dropping a single entity still apply.
+fn each_entity(
+    state: &mut State,
+    entities: Vec<EntityId>,
+    command: fn(String) -> Command,
+    result: &str,
+) -> Victory {
+    let mut separator = "\n";
+    for entity_id in entities {
+        let Some(name) = state
+            .get_entity(entity_id)
+            .map(|entity| entity.name.clone())
+        else {
+            continue;
+        };
+        let single = command(name.clone());
+        if state.triggered_event(&single).is_some() {
+            state.log(&format!("{separator}{name}:"));
+            let victory = state.run(&single);
+            if victory != Victory::None {
+                return victory;
+            }
+        } else {
+            if matches!(single, Command::Take(_)) {
+                state.take_entity(entity_id);
+            } else {
+                state.remove_from_inventory(entity_id);
+                state.add_entity_to_room(entity_id);
+            }
+            state.log(&format!("{separator}{name}: {result}."));
+        }
+        separator = "";
+    }
+    Victory::None
+}
@@ -1,17 +1,21 @@
 use crate::command::Command;
+use crate::id::{ActorId, EntityId, RoomId};
+use crate::script::Expr;
 use serde::{Deserialize, Serialize};
 
 #[derive(Debug, Serialize, Deserialize, Eq, PartialEq, Clone)]
 pub enum Condition {
     CommandIs(Command),
-    Location(usize),
-    And(usize, usize),
-    ObjectInInventory(usize),
-    Or(usize, usize),
-    NotCommandIs(Command),
-    NotLocation(usize),
-    NotAnd(usize, usize),
-    NotObjectInInventory(usize),
-    NotOr(usize, usize),
-    Actor(usize),
+    Location(RoomId),
+    ObjectInInventory(EntityId),
+    Actor(ActorId),
+    /// True if the actor is in the room the player is in.
+    ActorHere(ActorId),
+    /// True if all of the conditions are true, an empty list is always true.
+    All(Vec<Condition>),
+    /// True if any of the conditions is true, an empty list is never true.
+    Any(Vec<Condition>),
+    Not(Box<Condition>),
+    /// A condition written in the event script language.
+    Script(Expr),
 }
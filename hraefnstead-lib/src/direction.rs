@@ -1,24 +1,84 @@
 use serde::{Deserialize, Serialize};
 use std::fmt::Display;
 
-#[derive(Debug, Clone, Serialize, Deserialize, Hash, Eq, PartialEq)]
+/// A way out of a room: a compass direction, up or down, in or out, or an
+/// exit with a name of its own, like a door, a hatch or a ladder.
+///
+/// In world and save files directions are written as their names, e.g.
+/// `"North"`, `"NorthEast"` or `"Up"`; any other name is a named exit.
+#[derive(Debug, Clone, Serialize, Deserialize, Hash, Eq, PartialEq, Ord, PartialOrd)]
+#[serde(from = "String", into = "String")]
 pub enum Direction {
     North,
     South,
     East,
     West,
+    NorthEast,
+    NorthWest,
+    SouthEast,
+    SouthWest,
+    Up,
+    Down,
+    In,
+    Out,
+    /// An exit with a name, in lower case.
+    Named(String),
 }
 
 impl Direction {
+    /// All directions but the named exits.
+    pub const ALL: [Direction; 12] = [
+        Direction::North,
+        Direction::South,
+        Direction::East,
+        Direction::West,
+        Direction::NorthEast,
+        Direction::NorthWest,
+        Direction::SouthEast,
+        Direction::SouthWest,
+        Direction::Up,
+        Direction::Down,
+        Direction::In,
+        Direction::Out,
+    ];
+
+    /// The direction of a lower case name or abbreviation, like "north",
+    /// "ne" or "u". Named exits are no directions.
     pub fn from_str(s: &str) -> Option<Self> {
         match s {
-            "north" => Some(Direction::North),
-            "south" => Some(Direction::South),
-            "east" => Some(Direction::East),
-            "west" => Some(Direction::West),
+            "north" | "n" => Some(Direction::North),
+            "south" | "s" => Some(Direction::South),
+            "east" | "e" => Some(Direction::East),
+            "west" | "w" => Some(Direction::West),
+            "northeast" | "ne" => Some(Direction::NorthEast),
+            "northwest" | "nw" => Some(Direction::NorthWest),
+            "southeast" | "se" => Some(Direction::SouthEast),
+            "southwest" | "sw" => Some(Direction::SouthWest),
+            "up" | "u" => Some(Direction::Up),
+            "down" | "d" => Some(Direction::Down),
+            "in" => Some(Direction::In),
+            "out" => Some(Direction::Out),
             _ => None,
         }
     }
+
+    /// The direction of a name, or the exit with that name.
+    pub fn from_name(name: &str) -> Self {
+        let name = name.to_lowercase();
+        Direction::from_str(&name).unwrap_or(Direction::Named(name))
+    }
+}
+
+impl From<String> for Direction {
+    fn from(name: String) -> Self {
+        Direction::from_name(&name)
+    }
+}
+
+impl From<Direction> for String {
+    fn from(direction: Direction) -> Self {
+        direction.to_string()
+    }
 }
 
 impl Display for Direction {
@@ -28,6 +88,15 @@ impl Display for Direction {
             Direction::South => write!(f, "South"),
             Direction::East => write!(f, "East"),
             Direction::West => write!(f, "West"),
+            Direction::NorthEast => write!(f, "NorthEast"),
+            Direction::NorthWest => write!(f, "NorthWest"),
+            Direction::SouthEast => write!(f, "SouthEast"),
+            Direction::SouthWest => write!(f, "SouthWest"),
+            Direction::Up => write!(f, "Up"),
+            Direction::Down => write!(f, "Down"),
+            Direction::In => write!(f, "In"),
+            Direction::Out => write!(f, "Out"),
+            Direction::Named(name) => write!(f, "{name}"),
         }
     }
 }
@@ -0,0 +1,40 @@
+//! Doors between two rooms.
+//!
+//! A door stands in an exit of each of the two rooms it connects. While it
+//! is closed or locked the player cannot pass, and a locked door only opens
+//! after it is unlocked with its key.
+
+use crate::direction::Direction;
+use crate::id::{EntityId, RoomId};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, Hash, Eq, PartialEq)]
+pub enum DoorState {
+    #[default]
+    Open,
+    Closed,
+    Locked,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Door {
+    pub name: String,
+    pub aliases: HashSet<String>,
+    /// The rooms the door connects, each with the exit the door stands in.
+    pub sides: [(RoomId, Direction); 2],
+    /// The entity that locks and unlocks the door, if it has a lock.
+    pub key: Option<EntityId>,
+    /// The state of the door at the start of a game.
+    pub state: DoorState,
+}
+
+impl Door {
+    /// The exit of the room the door stands in, if it is in the room.
+    pub fn exit_in(&self, room: RoomId) -> Option<&Direction> {
+        self.sides
+            .iter()
+            .find(|(side, _)| *side == room)
+            .map(|(_, direction)| direction)
+    }
+}
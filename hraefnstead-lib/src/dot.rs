@@ -0,0 +1,263 @@
+//! Export of worlds as Graphviz DOT graphs.
+//!
+//! The room graph shows the rooms and their exits; exits that events open
+//! during the game are dashed and start at the room the event's condition
+//! requires, or at "any room". The event graph adds the events with the
+//! events they activate and deactivate, and the rooms, entities and actors
+//! their conditions depend on.
+
+use crate::command::Command;
+use crate::condition::Condition;
+use crate::direction::Direction;
+use crate::event::Event;
+use crate::id::{ActorId, EntityId, EventId, RoomId};
+use crate::script::{Expr, Script, Statement};
+use crate::world::World;
+
+use std::collections::BTreeSet;
+use std::fmt::Display;
+
+/// Render the room graph of a world, with `events` also the event graph.
+pub fn to_dot(world: &World, events: bool) -> String {
+    let mut dot = String::from("digraph world {\n    node [shape=box];\n");
+    for (room_id, room) in world.rooms.iter().enumerate() {
+        let style = if RoomId(room_id) == world.start {
+            ", style=bold"
+        } else {
+            ""
+        };
+        dot.push_str(&format!(
+            "    room{room_id} [label=\"{}\"{style}];\n",
+            escape(&room.name)
+        ));
+    }
+    for (room_id, room) in world.rooms.iter().enumerate() {
+        let mut exits: Vec<_> = room.get_exits().iter().collect();
+        exits.sort();
+        for (dir, target) in exits {
+            dot.push_str(&format!(
+                "    room{room_id} -> room{target} [label=\"{dir}\"];\n"
+            ));
+        }
+    }
+
+    let mut anywhere = false;
+    for (event_id, event) in world.events.iter().enumerate() {
+        let rooms = locations(&event.condition);
+        for effect in effects(event) {
+            let Effect::Exit(dir, target) = effect else {
+                continue;
+            };
+            let mut sources: Vec<String> = rooms.iter().map(|room| format!("room{room}")).collect();
+            if sources.is_empty() {
+                if !anywhere {
+                    dot.push_str("    anywhere [label=\"(any room)\", shape=plaintext];\n");
+                    anywhere = true;
+                }
+                sources.push("anywhere".to_string());
+            }
+            for source in sources {
+                dot.push_str(&format!(
+                    "    {source} -> room{target} [label=\"{dir} (event {event_id})\", style=dashed];\n"
+                ));
+            }
+        }
+    }
+
+    if events {
+        event_graph(world, &mut dot);
+    }
+    dot.push_str("}\n");
+    dot
+}
+
+fn event_graph(world: &World, dot: &mut String) {
+    let mut entities = BTreeSet::new();
+    let mut actors = BTreeSet::new();
+    for (event_id, event) in world.events.iter().enumerate() {
+        let active = if world.active_events.contains(&EventId(event_id)) {
+            ", peripheries=2"
+        } else {
+            ""
+        };
+        dot.push_str(&format!(
+            "    event{event_id} [label=\"event {event_id}\\n{}\", shape=note{active}];\n",
+            escape(&summary(&event.message))
+        ));
+        for effect in effects(event) {
+            match effect {
+                Effect::Activate(target) => dot.push_str(&format!(
+                    "    event{event_id} -> event{target} [label=\"activates\"];\n"
+                )),
+                Effect::Deactivate(target) => dot.push_str(&format!(
+                    "    event{event_id} -> event{target} [label=\"deactivates\", style=dotted];\n"
+                )),
+                Effect::Exit(_, _) => {}
+            }
+        }
+        let mut dependencies = BTreeSet::new();
+        dependencies_of(&event.condition, &mut dependencies);
+        for dependency in dependencies {
+            match &dependency {
+                Dependency::Entity(entity) => entities.insert(*entity),
+                Dependency::Actor(actor) => actors.insert(*actor),
+                Dependency::Room(_) => false,
+            };
+            dot.push_str(&format!(
+                "    {dependency} -> event{event_id} [color=gray];\n"
+            ));
+        }
+    }
+    for entity in entities {
+        let name = world.entities.get(&entity).map_or("?", |e| &e.name);
+        dot.push_str(&format!(
+            "    entity{entity} [label=\"{}\", shape=ellipse];\n",
+            escape(name)
+        ));
+    }
+    for actor in actors {
+        let name = world.actors.get(&actor).map_or("?", |a| &a.name);
+        dot.push_str(&format!(
+            "    actor{actor} [label=\"{}\", shape=diamond];\n",
+            escape(name)
+        ));
+    }
+}
+
+/// Escape a text for a quoted DOT string.
+fn escape(text: &str) -> String {
+    text.replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+/// The start of the first line of a message.
+fn summary(message: &str) -> String {
+    let line = message.lines().next().unwrap_or_default();
+    if line.chars().count() > 30 {
+        format!("{}...", line.chars().take(30).collect::<String>())
+    } else {
+        line.to_string()
+    }
+}
+
+/// The rooms a condition requires the player to be in.
+fn locations(condition: &Condition) -> Vec<RoomId> {
+    match condition {
+        Condition::Location(room) => vec![*room],
+        Condition::All(conditions) => conditions.iter().flat_map(locations).collect(),
+        _ => Vec::new(),
+    }
+}
+
+enum Effect {
+    Exit(Direction, RoomId),
+    Activate(EventId),
+    Deactivate(EventId),
+}
+
+/// The effects of an event's commands and script that shape the graphs.
+fn effects(event: &Event) -> Vec<Effect> {
+    let mut effects = Vec::new();
+    for command in event.command_stack.iter() {
+        match command {
+            Command::AddExit(dir, room) => effects.push(Effect::Exit(dir.clone(), *room)),
+            Command::ActivateEvent(event) => effects.push(Effect::Activate(*event)),
+            Command::DeActivateEvent(event) => effects.push(Effect::Deactivate(*event)),
+            _ => {}
+        }
+    }
+    script_effects(&event.script, &mut effects);
+    effects
+}
+
+fn script_effects(script: &Script, effects: &mut Vec<Effect>) {
+    for statement in script.0.iter() {
+        match statement {
+            Statement::Exit(dir, room) => effects.push(Effect::Exit(dir.clone(), *room)),
+            Statement::Activate(event) => effects.push(Effect::Activate(*event)),
+            Statement::Deactivate(event) => effects.push(Effect::Deactivate(*event)),
+            Statement::If(_, then, otherwise) => {
+                script_effects(then, effects);
+                script_effects(otherwise, effects);
+            }
+            _ => {}
+        }
+    }
+}
+
+#[derive(PartialEq, Eq, PartialOrd, Ord)]
+enum Dependency {
+    Room(RoomId),
+    Entity(EntityId),
+    Actor(ActorId),
+}
+
+impl Display for Dependency {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Dependency::Room(room) => write!(f, "room{room}"),
+            Dependency::Entity(entity) => write!(f, "entity{entity}"),
+            Dependency::Actor(actor) => write!(f, "actor{actor}"),
+        }
+    }
+}
+
+fn dependencies_of(condition: &Condition, dependencies: &mut BTreeSet<Dependency>) {
+    match condition {
+        Condition::Location(room) => {
+            dependencies.insert(Dependency::Room(*room));
+        }
+        Condition::ObjectInInventory(entity) => {
+            dependencies.insert(Dependency::Entity(*entity));
+        }
+        Condition::Actor(actor) | Condition::ActorHere(actor) => {
+            dependencies.insert(Dependency::Actor(*actor));
+        }
+        Condition::All(conditions) | Condition::Any(conditions) => {
+            for condition in conditions {
+                dependencies_of(condition, dependencies);
+            }
+        }
+        Condition::Not(condition) => dependencies_of(condition, dependencies),
+        Condition::Script(expr) => expr_dependencies(expr, dependencies),
+        Condition::CommandIs(_) => {}
+    }
+}
+
+fn expr_dependencies(expr: &Expr, dependencies: &mut BTreeSet<Dependency>) {
+    match expr {
+        Expr::Has(entity) | Expr::Here(entity) => {
+            dependencies.insert(Dependency::Entity(*entity));
+        }
+        Expr::At(room) => {
+            dependencies.insert(Dependency::Room(*room));
+        }
+        Expr::Not(expr) => expr_dependencies(expr, dependencies),
+        Expr::And(left, right) | Expr::Or(left, right) => {
+            expr_dependencies(left, dependencies);
+            expr_dependencies(right, dependencies);
+        }
+        Expr::Bool(_) | Expr::Flag(_) | Expr::Count(_, _, _) => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn exits_opened_by_events_are_dashed() {
+        let world = World::default();
+        let dot = to_dot(&world, false);
+        assert!(dot.contains("room0 [label=\"Entrance\", style=bold];"));
+        assert!(dot.contains("room2 -> room1 [label=\"West\"];"));
+        assert!(dot.contains("room2 -> room3 [label=\"North (event 4)\", style=dashed];"));
+        assert!(!dot.contains("event0"));
+
+        let dot = to_dot(&world, true);
+        assert!(dot.contains("event2 -> event4 [label=\"activates\"];"));
+        assert!(dot.contains("event0 -> event0 [label=\"deactivates\", style=dotted];"));
+        assert!(dot.contains("actor1 -> event5 [color=gray];"));
+    }
+}
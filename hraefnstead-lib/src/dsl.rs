@@ -0,0 +1,980 @@
+//! A text language for writing worlds, compiled to a [`WorldSource`].
+//!
+//! Every line is one statement; indented lines belong to the room above
+//! them. Rooms, things and events are referred to by their names, which
+//! may be used before they are declared:
+//!
+//! ```text
+//! # The world id and version, both optional, and the language
+//! World "crypt" version 1
+//! Language "en"
+//! Start in Hall
+//!
+//! Room "Hall": "A draughty hall."
+//!     exit east to Dusty Chamber
+//!     Thing "A copper coin" (coin, copper): "An old, dirty coin."
+//! Room "Dusty Chamber": "There is a bed in the chamber."
+//!     exit west to Hall
+//!     Actor "Goblin" (goblin): "A goblin guards the door."
+//!
+//! Thing "A golden coin" (golden coin, gold): "It shines."
+//! Recipe coin makes golden coin
+//! Understand "grab" as take
+//!
+//! When examine bed in Dusty Chamber: say "Below the pillow lies a coin."; place coin; once
+//! Event "bribe" when use coin in Dusty Chamber while Goblin is here: say "..."; remove goblin
+//! Inactive event "thanks" when look: say "Thank you!"
+//! ```
+//!
+//! * `Thing` and `Actor` take the name, aliases in parentheses and the
+//!   description; indented below a room they are placed in it, `contains`
+//!   places things declared elsewhere
+//! * exits lead into a direction, `north`, `south`, `east`, `west`, the
+//!   diagonals like `northeast`, `up`, `down`, `in` and `out`, or through a
+//!   named exit given in quotes, e.g. `exit "ladder" to Attic`
+//! * the key of a room or event is its name in lower case with `_` between
+//!   the words, e.g. `dusty_chamber`, the key of a thing or actor its first
+//!   alias; scripts refer to them by these keys
+//! * events start with `When`, optionally preceded by `Event "name"` to refer
+//!   to it from other events and `Inactive` if it is not active at the start
+//! * an event triggers on a command (`look`, `inventory`, `go north`, `north`,
+//!   or one of `examine`, `take`, `drop`, `use`, `eat`, `attack` and `craft`
+//!   followed by what the player types, `use coin on machine`, `give chips to
+//!   goblin`, `put coin in slot` or `attack goblin with sword` for two objects),
+//!   or on any of several commands separated by `or`, e.g. `use coin or put
+//!   coin in slot`, followed by any of the conditions
+//!   `in room`, `holding thing`, `while actor is here` and `if "script"`, each
+//!   of which can be negated with `not`
+//! * after the `:` follow the effects separated by `;`: `say "text"`,
+//!   `place thing`, `consume thing`, `remove actor`, `open north to room`,
+//!   `enable event`, `disable event`, `once` (disable this event), `win`,
+//!   `lose` and `script "statements"`, see [`crate::script`]
+//! * `Door` takes the name and aliases like a thing, followed by the two
+//!   rooms with the exit it stands in, whether it is `open` (the default),
+//!   `closed` or `locked`, and the thing that locks and unlocks it, e.g.
+//!   `Door "oak door" (door) between Hall north and Vault south locked with key`;
+//!   the door adds both exits
+//! * `Understand "word" as verb` adds a synonym for one of the verbs of
+//!   [`crate::verb`]
+//! * `Language "de"` picks the [`crate::locale`] of the engine's messages
+//!
+//! Everything after `#` on a line is a comment.
+
+use crate::command::Command;
+use crate::direction::Direction;
+use crate::door::DoorState;
+use crate::parser::two_object_command;
+use crate::script::{tokenize, Token};
+use crate::source::{
+    CommandSource, ConditionExpr, DoorSource, EventSource, RoomSource, ThingSource, WorldSource,
+};
+
+use std::fmt::Display;
+
+/// An error in a world written in the world language.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DslError {
+    pub line: usize,
+    pub column: usize,
+    pub message: String,
+}
+
+impl Display for DslError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "line {}, column {}: {}",
+            self.line, self.column, self.message
+        )
+    }
+}
+
+/// Compile a world, reporting all errors found.
+pub fn compile(source: &str) -> Result<WorldSource, Vec<DslError>> {
+    let mut compiler = Compiler::default();
+    let mut lines = Vec::new();
+    for (number, text) in source.lines().enumerate() {
+        match tokenize(text) {
+            Ok(tokens) if tokens.is_empty() => {}
+            Ok(tokens) => lines.push(Line {
+                number: number + 1,
+                indented: text.starts_with(char::is_whitespace),
+                end: text.chars().count() + 1,
+                tokens: tokens
+                    .into_iter()
+                    .map(|(token, _, column)| (token, column))
+                    .collect(),
+            }),
+            Err(error) => compiler.errors.push(DslError {
+                line: number + 1,
+                column: error.column,
+                message: error.message,
+            }),
+        }
+    }
+    let mut deferred = Vec::new();
+    for line in lines.iter() {
+        let mut cursor = Cursor::new(line);
+        match compiler.declare(&mut cursor) {
+            Ok(Some(reference)) => deferred.push((cursor, reference)),
+            Ok(None) => {}
+            Err(error) => compiler.errors.push(error),
+        }
+    }
+    for (mut cursor, reference) in deferred {
+        if let Err(error) = compiler.resolve(&mut cursor, reference) {
+            compiler.errors.push(error);
+        }
+    }
+    if compiler.world.start.is_empty() {
+        match compiler.world.rooms.first() {
+            Some(room) => compiler.world.start = room.key.clone(),
+            None => compiler.errors.push(DslError {
+                line: 1,
+                column: 1,
+                message: "a world needs at least one room".to_string(),
+            }),
+        }
+    }
+    if compiler.errors.is_empty() {
+        Ok(compiler.world)
+    } else {
+        compiler.errors.sort_by_key(|e| (e.line, e.column));
+        Err(compiler.errors)
+    }
+}
+
+/// The key for a name: lower case words joined by `_`.
+fn key_of(name: &str) -> String {
+    words_of(name).join("_")
+}
+
+/// The words of a name in lower case, without punctuation.
+fn words_of(name: &str) -> Vec<String> {
+    name.split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(str::to_lowercase)
+        .collect()
+}
+
+/// Words that end the name of what a command acts on.
+const CONDITION_WORDS: [&str; 6] = ["in", "holding", "while", "if", "not", "and"];
+
+struct Line {
+    number: usize,
+    indented: bool,
+    /// The column after the last character.
+    end: usize,
+    tokens: Vec<(Token, usize)>,
+}
+
+struct Cursor<'l> {
+    line: &'l Line,
+    position: usize,
+}
+
+impl<'l> Cursor<'l> {
+    fn new(line: &'l Line) -> Self {
+        Self { line, position: 0 }
+    }
+
+    fn peek(&self) -> Option<&'l Token> {
+        self.line.tokens.get(self.position).map(|(token, _)| token)
+    }
+
+    fn column(&self) -> usize {
+        match self.line.tokens.get(self.position) {
+            Some((_, column)) => *column,
+            None => self.line.end,
+        }
+    }
+
+    fn error_at(&self, column: usize, message: String) -> DslError {
+        DslError {
+            line: self.line.number,
+            column,
+            message,
+        }
+    }
+
+    fn error(&self, message: &str) -> DslError {
+        let found = match self.peek() {
+            Some(Token::Word(word)) => format!("'{word}'"),
+            Some(token) => token.to_string(),
+            None => "the end of the line".to_string(),
+        };
+        self.error_at(self.column(), format!("{message}, found {found}"))
+    }
+
+    /// The next token as a lower case word, if it is one.
+    fn peek_word(&self) -> Option<String> {
+        match self.peek() {
+            Some(Token::Word(word)) => Some(word.to_lowercase()),
+            Some(Token::Number(number)) => Some(number.to_string()),
+            _ => None,
+        }
+    }
+
+    fn word(&mut self, what: &str) -> Result<String, DslError> {
+        let word = self
+            .peek_word()
+            .ok_or_else(|| self.error(&format!("expected {what}")))?;
+        self.position += 1;
+        Ok(word)
+    }
+
+    fn accept(&mut self, word: &str) -> bool {
+        let found = self.peek_word().as_deref() == Some(word);
+        if found {
+            self.position += 1;
+        }
+        found
+    }
+
+    fn expect(&mut self, word: &str) -> Result<(), DslError> {
+        if self.accept(word) {
+            Ok(())
+        } else {
+            Err(self.error(&format!("expected '{word}'")))
+        }
+    }
+
+    fn accept_symbol(&mut self, symbol: &str) -> bool {
+        let found = matches!(self.peek(), Some(Token::Symbol(s)) if *s == symbol);
+        if found {
+            self.position += 1;
+        }
+        found
+    }
+
+    fn expect_symbol(&mut self, symbol: &str) -> Result<(), DslError> {
+        if self.accept_symbol(symbol) {
+            Ok(())
+        } else {
+            Err(self.error(&format!("expected '{symbol}'")))
+        }
+    }
+
+    fn text(&mut self, what: &str) -> Result<String, DslError> {
+        match self.peek() {
+            Some(Token::Text(text)) => {
+                self.position += 1;
+                Ok(text.clone())
+            }
+            _ => Err(self.error(&format!("expected {what} in quotes"))),
+        }
+    }
+
+    /// The words up to the next symbol or text, with the column of the first.
+    fn words(&self) -> (Vec<String>, usize) {
+        let mut words = Vec::new();
+        let mut cursor = Cursor {
+            line: self.line,
+            position: self.position,
+        };
+        while let Some(word) = cursor.peek_word() {
+            words.push(word);
+            cursor.position += 1;
+        }
+        (words, self.column())
+    }
+
+    /// The aliases in parentheses, if there are any.
+    fn aliases(&mut self) -> Result<Vec<String>, DslError> {
+        let mut aliases = Vec::new();
+        if self.accept_symbol("(") {
+            loop {
+                let (words, _) = self.words();
+                if words.is_empty() {
+                    return Err(self.error("expected an alias"));
+                }
+                self.position += words.len();
+                aliases.push(words.join(" "));
+                if !self.accept_symbol(",") {
+                    break;
+                }
+            }
+            self.expect_symbol(")")?;
+        }
+        Ok(aliases)
+    }
+
+    fn is_at(&self, symbol: &str) -> bool {
+        self.peek().is_none() || matches!(self.peek(), Some(Token::Symbol(s)) if *s == symbol)
+    }
+
+    fn end(&self) -> Result<(), DslError> {
+        match self.peek() {
+            None => Ok(()),
+            Some(_) => Err(self.error("expected the end of the line")),
+        }
+    }
+}
+
+/// The names rooms, things or events can be referred to by.
+struct Names {
+    kind: &'static str,
+    entries: Vec<(String, Vec<Vec<String>>)>,
+}
+
+impl Names {
+    fn new(kind: &'static str) -> Self {
+        Self {
+            kind,
+            entries: Vec::new(),
+        }
+    }
+
+    fn contains(&self, key: &str) -> bool {
+        self.entries.iter().any(|(k, _)| k == key)
+    }
+
+    /// The longest name at the start of `words`, with its number of words.
+    fn prefix(
+        &self,
+        words: &[String],
+        column: usize,
+        line: &Line,
+    ) -> Result<(String, usize), DslError> {
+        let mut found: Option<(&str, usize)> = None;
+        for (key, names) in self.entries.iter() {
+            for name in names {
+                if name.len() > words.len() || name[..] != words[..name.len()] {
+                    continue;
+                }
+                match found {
+                    Some((other, len)) if len == name.len() && other != key => {
+                        return Err(DslError {
+                            line: line.number,
+                            column,
+                            message: format!(
+                                "'{}' could be the {} '{other}' or '{key}'",
+                                words[..len].join(" "),
+                                self.kind
+                            ),
+                        });
+                    }
+                    Some((_, len)) if len >= name.len() => {}
+                    _ => found = Some((key, name.len())),
+                }
+            }
+        }
+        match found {
+            Some((key, len)) => Ok((key.to_string(), len)),
+            None if words.is_empty() => Err(DslError {
+                line: line.number,
+                column,
+                message: format!("expected the name of a {}", self.kind),
+            }),
+            None => Err(DslError {
+                line: line.number,
+                column,
+                message: format!("unknown {} '{}'", self.kind, words.join(" ")),
+            }),
+        }
+    }
+}
+
+enum Reference {
+    Start,
+    Exit(usize),
+    Contains(usize),
+    Door(usize),
+    Recipe,
+    Event(usize),
+}
+
+struct Compiler {
+    world: WorldSource,
+    rooms: Names,
+    entities: Names,
+    actors: Names,
+    events: Names,
+    /// The room the `exit` and `contains` lines belong to.
+    room: Option<usize>,
+    errors: Vec<DslError>,
+}
+
+impl Default for Compiler {
+    fn default() -> Self {
+        Self {
+            world: WorldSource {
+                id: String::new(),
+                version: 0,
+                start: String::new(),
+                rooms: Vec::new(),
+                entities: Vec::new(),
+                actors: Vec::new(),
+                doors: Vec::new(),
+                craft_recipes: Default::default(),
+                conditions: Vec::new(),
+                events: Vec::new(),
+                active_events: Vec::new(),
+                synonyms: Default::default(),
+                locale: Default::default(),
+            },
+            rooms: Names::new("room"),
+            entities: Names::new("thing"),
+            actors: Names::new("actor"),
+            events: Names::new("event"),
+            room: None,
+            errors: Vec::new(),
+        }
+    }
+}
+
+impl Compiler {
+    /// Record the declarations of a line; lines referring to other
+    /// declarations are resolved once all names are known.
+    fn declare(&mut self, cursor: &mut Cursor) -> Result<Option<Reference>, DslError> {
+        let column = cursor.column();
+        let keyword = cursor.word("a statement")?;
+        let reference = match keyword.as_str() {
+            "world" => {
+                self.world.id = cursor.text("the id of the world")?;
+                if cursor.accept("version") {
+                    match cursor.peek() {
+                        Some(Token::Number(version)) if *version >= 0 => {
+                            self.world.version = *version as u32;
+                            cursor.position += 1;
+                        }
+                        _ => return Err(cursor.error("expected a version number")),
+                    }
+                }
+                cursor.end()?;
+                None
+            }
+            "start" => {
+                cursor.expect("in")?;
+                Some(Reference::Start)
+            }
+            "language" => {
+                let column = cursor.column();
+                let language = cursor.text("the language")?;
+                self.world.locale = language
+                    .parse()
+                    .map_err(|error| cursor.error_at(column, error))?;
+                cursor.end()?;
+                None
+            }
+            "understand" => {
+                let synonym = cursor.text("the new word")?;
+                cursor.expect("as")?;
+                let verb = cursor.word("a verb")?;
+                cursor.end()?;
+                self.world.synonyms.entry(verb).or_default().push(synonym);
+                None
+            }
+            "room" => {
+                let name = cursor.text("the name of the room")?;
+                cursor.expect_symbol(":")?;
+                let description = cursor.text("the description of the room")?;
+                cursor.end()?;
+                let key = key_of(&name);
+                self.declare_name(cursor, column, Kind::Room, &key, vec![words_of(&name)])?;
+                self.room = Some(self.world.rooms.len());
+                self.world.rooms.push(RoomSource {
+                    key,
+                    name,
+                    description,
+                    entities: Vec::new(),
+                    actors: Vec::new(),
+                    exits: Default::default(),
+                });
+                None
+            }
+            "thing" | "actor" => {
+                let name = cursor.text(&format!("the name of the {keyword}"))?;
+                let aliases = cursor.aliases()?;
+                cursor.expect_symbol(":")?;
+                let description = cursor.text(&format!("the description of the {keyword}"))?;
+                cursor.end()?;
+                let key = key_of(aliases.first().unwrap_or(&name));
+                let mut names = vec![words_of(&name), words_of(&key)];
+                if let [article, rest @ ..] = &names[0][..] {
+                    if ["a", "an", "the"].contains(&article.as_str()) && !rest.is_empty() {
+                        names.push(rest.to_vec());
+                    }
+                }
+                names.extend(aliases.iter().map(|alias| words_of(alias)));
+                let thing = ThingSource {
+                    key: key.clone(),
+                    name,
+                    description,
+                    aliases,
+                };
+                let kind = if keyword == "thing" {
+                    Kind::Entity
+                } else {
+                    Kind::Actor
+                };
+                self.declare_name(cursor, column, kind, &key, names)?;
+                let room = match self.room {
+                    Some(room) if cursor.line.indented => Some(&mut self.world.rooms[room]),
+                    _ => None,
+                };
+                if let Kind::Entity = kind {
+                    if let Some(room) = room {
+                        room.entities.push(key);
+                    }
+                    self.world.entities.push(thing);
+                } else {
+                    if let Some(room) = room {
+                        room.actors.push(key);
+                    }
+                    self.world.actors.push(thing);
+                }
+                None
+            }
+            "exit" | "contains" => {
+                let Some(room) = self.room else {
+                    return Err(cursor.error_at(column, format!("'{keyword}' must follow a room")));
+                };
+                Some(if keyword == "exit" {
+                    Reference::Exit(room)
+                } else {
+                    Reference::Contains(room)
+                })
+            }
+            "door" => {
+                let name = cursor.text("the name of the door")?;
+                let aliases = cursor.aliases()?;
+                let key = key_of(aliases.first().unwrap_or(&name));
+                if self.world.doors.iter().any(|door| door.key == key) {
+                    return Err(cursor.error_at(column, format!("door '{key}' is declared twice")));
+                }
+                self.world.doors.push(DoorSource {
+                    key,
+                    name,
+                    aliases,
+                    sides: Default::default(),
+                    lock: None,
+                    state: DoorState::Open,
+                });
+                Some(Reference::Door(self.world.doors.len() - 1))
+            }
+            "recipe" => Some(Reference::Recipe),
+            "inactive" | "event" | "when" => {
+                let active = keyword != "inactive";
+                let name = if keyword == "event" || (!active && cursor.accept("event")) {
+                    Some(cursor.text("the name of the event")?)
+                } else {
+                    None
+                };
+                if keyword != "when" {
+                    cursor.expect("when")?;
+                }
+                let key = match &name {
+                    Some(name) => key_of(name),
+                    None => format!("event_{}", cursor.line.number),
+                };
+                let mut names = vec![words_of(&key)];
+                if let Some(name) = &name {
+                    names.push(words_of(name));
+                }
+                self.declare_name(cursor, column, Kind::Event, &key, names)?;
+                if active {
+                    self.world.active_events.push(key.clone());
+                }
+                self.world.events.push(EventSource {
+                    key,
+                    condition: ConditionExpr::All(Vec::new()),
+                    message: String::new(),
+                    command_stack: Vec::new(),
+                    script: None,
+                });
+                Some(Reference::Event(self.world.events.len() - 1))
+            }
+            _ => {
+                return Err(cursor.error_at(column, format!("unknown statement '{keyword}'")));
+            }
+        };
+        Ok(reference)
+    }
+
+    fn declare_name(
+        &mut self,
+        cursor: &Cursor,
+        column: usize,
+        kind: Kind,
+        key: &str,
+        names: Vec<Vec<String>>,
+    ) -> Result<(), DslError> {
+        let names_of_kind = self.names(kind);
+        if names_of_kind.contains(key) {
+            return Err(cursor.error_at(
+                column,
+                format!("{} '{key}' is declared twice", names_of_kind.kind),
+            ));
+        }
+        names_of_kind.entries.push((key.to_string(), names));
+        Ok(())
+    }
+
+    fn names(&mut self, kind: Kind) -> &mut Names {
+        match kind {
+            Kind::Room => &mut self.rooms,
+            Kind::Entity => &mut self.entities,
+            Kind::Actor => &mut self.actors,
+            Kind::Event => &mut self.events,
+        }
+    }
+
+    /// The key of the declaration named at the cursor, using up the words of its name.
+    fn name(&mut self, cursor: &mut Cursor, kind: Kind) -> Result<String, DslError> {
+        let (words, column) = cursor.words();
+        let (key, len) = self.names(kind).prefix(&words, column, cursor.line)?;
+        cursor.position += len;
+        Ok(key)
+    }
+
+    /// Like [`Compiler::name`], but the name has to extend up to `end`.
+    fn full_name(
+        &mut self,
+        cursor: &mut Cursor,
+        kind: Kind,
+        end: &str,
+    ) -> Result<String, DslError> {
+        let key = self.name(cursor, kind)?;
+        if cursor.is_at(end) {
+            Ok(key)
+        } else {
+            let kind = self.names(kind).kind;
+            Err(cursor.error(&format!("expected the name of a {kind} only")))
+        }
+    }
+
+    fn resolve(&mut self, cursor: &mut Cursor, reference: Reference) -> Result<(), DslError> {
+        match reference {
+            Reference::Start => {
+                self.world.start = self.full_name(cursor, Kind::Room, "")?;
+            }
+            Reference::Exit(room) => {
+                let direction = self.direction(cursor)?;
+                cursor.expect("to")?;
+                let target = self.full_name(cursor, Kind::Room, "")?;
+                if self.world.rooms[room].exits.contains_key(&direction) {
+                    return Err(cursor.error_at(
+                        cursor.line.tokens[1].1,
+                        format!("the room already has an exit {direction}"),
+                    ));
+                }
+                self.world.rooms[room].exits.insert(direction, target);
+            }
+            Reference::Contains(room) => loop {
+                let (words, column) = cursor.words();
+                if let Ok((key, len)) = self.entities.prefix(&words, column, cursor.line) {
+                    cursor.position += len;
+                    self.world.rooms[room].entities.push(key);
+                } else {
+                    let key = self.name(cursor, Kind::Actor).map_err(|_| {
+                        cursor.error_at(
+                            column,
+                            format!("unknown thing or actor '{}'", words.join(" ")),
+                        )
+                    })?;
+                    self.world.rooms[room].actors.push(key);
+                }
+                if !cursor.accept_symbol(",") {
+                    cursor.end()?;
+                    break;
+                }
+            },
+            Reference::Door(door) => {
+                cursor.expect("between")?;
+                for side in 0..2 {
+                    if side == 1 {
+                        cursor.expect("and")?;
+                    }
+                    let room = self.name(cursor, Kind::Room)?;
+                    let direction = self.direction(cursor)?;
+                    self.world.doors[door].sides.insert(room, direction);
+                }
+                let column = cursor.column();
+                if self.world.doors[door].sides.len() < 2 {
+                    return Err(cursor.error_at(
+                        column,
+                        "a door must connect two different rooms".to_string(),
+                    ));
+                }
+                let state = match cursor.peek_word().as_deref() {
+                    Some("open") => Some(DoorState::Open),
+                    Some("closed") => Some(DoorState::Closed),
+                    Some("locked") => Some(DoorState::Locked),
+                    _ => None,
+                };
+                if let Some(state) = state {
+                    cursor.position += 1;
+                    self.world.doors[door].state = state;
+                }
+                if cursor.accept("with") {
+                    self.world.doors[door].lock = Some(self.full_name(cursor, Kind::Entity, "")?);
+                }
+                cursor.end()?;
+            }
+            Reference::Recipe => {
+                let ingredient = self.name(cursor, Kind::Entity)?;
+                cursor.expect("makes")?;
+                let product = self.full_name(cursor, Kind::Entity, "")?;
+                self.world.craft_recipes.insert(ingredient, product);
+            }
+            Reference::Event(event) => self.event(cursor, event)?,
+        }
+        Ok(())
+    }
+
+    fn direction(&self, cursor: &mut Cursor) -> Result<Direction, DslError> {
+        if let Some(Token::Text(_)) = cursor.peek() {
+            return Ok(Direction::Named(cursor.text("")?.to_lowercase()));
+        }
+        let column = cursor.column();
+        let word = cursor.word("a direction")?;
+        Direction::from_str(&word)
+            .ok_or_else(|| cursor.error_at(column, format!("unknown direction '{word}'")))
+    }
+
+    fn event(&mut self, cursor: &mut Cursor, event: usize) -> Result<(), DslError> {
+        let mut conditions = Vec::new();
+        if let Some(command) = self.command(cursor)? {
+            let mut commands = vec![ConditionExpr::CommandIs(command)];
+            while cursor.accept("or") {
+                let Some(command) = self.command(cursor)? else {
+                    return Err(cursor.error("expected a command"));
+                };
+                commands.push(ConditionExpr::CommandIs(command));
+            }
+            conditions.push(if commands.len() == 1 {
+                commands.remove(0)
+            } else {
+                ConditionExpr::Any(commands)
+            });
+        }
+        while !cursor.is_at(":") {
+            cursor.accept("and");
+            let negated = cursor.accept("not");
+            let condition = match cursor.word("a condition")?.as_str() {
+                "in" => ConditionExpr::Location(self.name(cursor, Kind::Room)?),
+                "holding" => ConditionExpr::ObjectInInventory(self.name(cursor, Kind::Entity)?),
+                "while" => {
+                    let actor = self.name(cursor, Kind::Actor)?;
+                    cursor.expect("is")?;
+                    cursor.expect("here")?;
+                    ConditionExpr::ActorHere(actor)
+                }
+                "if" => ConditionExpr::Script(cursor.text("a script condition")?),
+                _ => {
+                    cursor.position -= 1;
+                    return Err(cursor.error("expected 'in', 'holding', 'while' or 'if'"));
+                }
+            };
+            conditions.push(if negated {
+                ConditionExpr::Not(Box::new(condition))
+            } else {
+                condition
+            });
+        }
+        if conditions.is_empty() {
+            return Err(cursor.error("expected a command or a condition"));
+        }
+        cursor.expect_symbol(":")?;
+
+        let key = self.world.events[event].key.clone();
+        let mut messages = Vec::new();
+        let mut command_stack = Vec::new();
+        let mut script = None;
+        while cursor.peek().is_some() {
+            if cursor.accept_symbol(";") {
+                continue;
+            }
+            let column = cursor.column();
+            let effect = cursor.word("an effect")?;
+            match effect.as_str() {
+                "say" => messages.push(cursor.text("the message")?),
+                "place" => command_stack.push(CommandSource::AddItemToRoom(self.full_name(
+                    cursor,
+                    Kind::Entity,
+                    ";",
+                )?)),
+                "consume" => command_stack.push(CommandSource::Consume(self.full_name(
+                    cursor,
+                    Kind::Entity,
+                    ";",
+                )?)),
+                "remove" => command_stack.push(CommandSource::RemoveActor(self.full_name(
+                    cursor,
+                    Kind::Actor,
+                    ";",
+                )?)),
+                "open" => {
+                    let direction = self.direction(cursor)?;
+                    cursor.expect("to")?;
+                    let room = self.full_name(cursor, Kind::Room, ";")?;
+                    command_stack.push(CommandSource::AddExit(direction, room));
+                }
+                "enable" => command_stack.push(CommandSource::ActivateEvent(self.full_name(
+                    cursor,
+                    Kind::Event,
+                    ";",
+                )?)),
+                "disable" => command_stack.push(CommandSource::DeActivateEvent(self.full_name(
+                    cursor,
+                    Kind::Event,
+                    ";",
+                )?)),
+                "once" => command_stack.push(CommandSource::DeActivateEvent(key.clone())),
+                "win" => command_stack.push(CommandSource::Other(Command::Won)),
+                "lose" => command_stack.push(CommandSource::Other(Command::GameOver)),
+                "script" => {
+                    if script.is_some() {
+                        return Err(
+                            cursor.error_at(column, "an event has only one script".to_string())
+                        );
+                    }
+                    script = Some(cursor.text("the script")?);
+                }
+                _ => return Err(cursor.error_at(column, format!("unknown effect '{effect}'"))),
+            }
+            if !cursor.is_at(";") {
+                return Err(cursor.error("expected ';'"));
+            }
+        }
+
+        let event = &mut self.world.events[event];
+        event.condition = if conditions.len() == 1 {
+            conditions.remove(0)
+        } else {
+            ConditionExpr::All(conditions)
+        };
+        event.message = messages.join("\n");
+        event.command_stack = command_stack;
+        event.script = script;
+        Ok(())
+    }
+
+    /// The command an event triggers on, if it names one.
+    fn command(&mut self, cursor: &mut Cursor) -> Result<Option<Command>, DslError> {
+        let Some(verb) = cursor.peek_word() else {
+            return Ok(None);
+        };
+        if CONDITION_WORDS.contains(&verb.as_str()) {
+            return Ok(None);
+        }
+        let column = cursor.column();
+        cursor.position += 1;
+        let command = match verb.as_str() {
+            "look" => Command::Look,
+            "inventory" => Command::Inventory,
+            "go" => Command::Move(self.direction(cursor)?),
+            "examine" | "take" | "drop" | "use" | "eat" | "attack" | "craft" | "give" | "put" => {
+                let mut noun = Vec::new();
+                while let Some(word) = cursor.peek_word() {
+                    // The `in` of "put coin in slot" is not a condition.
+                    let preposition = verb == "put" && word == "in" && !noun.contains(&word);
+                    if (CONDITION_WORDS.contains(&word.as_str()) || word == "or") && !preposition {
+                        break;
+                    }
+                    noun.push(word);
+                    cursor.position += 1;
+                }
+                if noun.is_empty() {
+                    return Err(cursor.error(&format!("expected what to {verb}")));
+                }
+                let noun = noun.join(" ");
+                if let Some(command) = two_object_command(&verb, &noun) {
+                    return Ok(Some(command));
+                }
+                match verb.as_str() {
+                    "examine" => Command::Examine(noun),
+                    "take" => Command::Take(noun),
+                    "drop" => Command::Drop(noun),
+                    "use" => Command::Use(noun),
+                    "eat" => Command::Eat(noun),
+                    "attack" => Command::Attack(noun),
+                    "craft" => Command::Craft(noun),
+                    _ => {
+                        return Err(cursor.error_at(
+                            column,
+                            format!("expected two objects after '{verb}', like in 'give chips to goblin' or 'put coin in slot'"),
+                        ))
+                    }
+                }
+            }
+            _ => match Direction::from_str(&verb) {
+                Some(direction) => Command::Move(direction),
+                None => return Err(cursor.error_at(column, format!("unknown command '{verb}'"))),
+            },
+        };
+        Ok(Some(command))
+    }
+}
+
+#[derive(Clone, Copy)]
+enum Kind {
+    Room,
+    Entity,
+    Actor,
+    Event,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::world::World;
+
+    #[test]
+    fn compiles_the_example_world() {
+        let source = compile(include_str!("../worlds/hraefnstead.story")).unwrap();
+        assert_eq!(source.start, "entrance");
+        assert_eq!(source.rooms[3].key, "treasure_room");
+        assert_eq!(source.rooms[3].entities, vec!["gold"]);
+        assert_eq!(source.craft_recipes["gold"], "armor");
+        assert_eq!(source.synonyms["take"], ["get", "grab"]);
+        assert_eq!(
+            source.events[0].condition,
+            ConditionExpr::All(vec![
+                ConditionExpr::CommandIs(Command::Examine("bed".to_string())),
+                ConditionExpr::Location("chamber".to_string()),
+            ])
+        );
+        assert_eq!(
+            source.events[0].command_stack[1],
+            CommandSource::DeActivateEvent("find_coin".to_string())
+        );
+        let world: World = source.resolve().unwrap();
+        assert!(world.validate().iter().all(|d| !d.is_error()));
+    }
+
+    #[test]
+    fn example_world_matches_the_default_world() {
+        let story = compile(include_str!("../worlds/hraefnstead.story"))
+            .unwrap()
+            .resolve()
+            .unwrap();
+        let default = World::default();
+        assert_eq!(story.events.len(), default.events.len());
+        for (compiled, bundled) in story.events.iter().zip(default.events.iter()) {
+            assert_eq!(compiled.condition, bundled.condition);
+            assert_eq!(compiled.command_stack, bundled.command_stack);
+        }
+        assert_eq!(story.active_events, default.active_events);
+    }
+
+    #[test]
+    fn reports_errors_with_their_position() {
+        let errors = compile(
+            "Room \"Hall\": \"A hall.\"\n    exit sideways to Hall\n    exit north to Cellar\n\
+             When examine bed in Hall: say \"Hmm\"; jump\nRoom \"Hall\" \"No colon\"",
+        )
+        .unwrap_err();
+        let errors: Vec<String> = errors.iter().map(DslError::to_string).collect();
+        assert_eq!(
+            errors,
+            vec![
+                "line 2, column 10: unknown direction 'sideways'",
+                "line 3, column 19: unknown room 'cellar'",
+                "line 4, column 38: unknown effect 'jump'",
+                "line 5, column 13: expected ':', found a text",
+            ]
+        );
+    }
+}
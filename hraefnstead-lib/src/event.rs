@@ -1,19 +1,30 @@
 use crate::command::Command;
+use crate::condition::Condition;
+use crate::script::Script;
 use serde::{Deserialize, Serialize};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Event {
-    pub condition_id: usize,
+    pub condition: Condition,
     pub message: String,
     pub command_stack: Vec<Command>,
+    /// Run by a `Command::RunScript` of this event.
+    #[serde(default)]
+    pub script: Script,
 }
 
 impl Event {
-    pub fn new(condition_id: usize, message: String, command_stack: Vec<Command>) -> Self {
+    pub fn new(
+        condition: Condition,
+        message: String,
+        command_stack: Vec<Command>,
+        script: Script,
+    ) -> Self {
         Self {
-            condition_id,
+            condition,
             message,
             command_stack,
+            script,
         }
     }
 }
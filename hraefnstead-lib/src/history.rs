@@ -0,0 +1,70 @@
+//! The inputs of the player during a session, for repeating and listing
+//! them.
+
+use crate::command::Command;
+
+use std::collections::VecDeque;
+
+/// How many inputs the history keeps.
+pub const HISTORY_LIMIT: usize = 50;
+
+/// An input of the player and the command it was parsed into.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Entry {
+    pub input: String,
+    pub command: Command,
+}
+
+/// The latest inputs, oldest first. Beyond [`HISTORY_LIMIT`] entries the
+/// oldest ones are dropped.
+#[derive(Debug, Clone, Default)]
+pub struct History {
+    entries: VecDeque<Entry>,
+}
+
+impl History {
+    pub fn push(&mut self, input: &str, command: Command) {
+        if self.entries.len() == HISTORY_LIMIT {
+            self.entries.pop_front();
+        }
+        self.entries.push_back(Entry {
+            input: input.to_string(),
+            command,
+        });
+    }
+
+    pub fn last(&self) -> Option<&Entry> {
+        self.entries.back()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Entry> {
+        self.entries.iter()
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keeps_the_latest_inputs() {
+        let mut history = History::default();
+        for n in 0..HISTORY_LIMIT + 2 {
+            history.push(&format!("look {n}"), Command::Look);
+        }
+        assert_eq!(history.len(), HISTORY_LIMIT);
+        assert_eq!(history.iter().next().unwrap().input, "look 2");
+        assert_eq!(
+            history.last().unwrap().input,
+            format!("look {}", HISTORY_LIMIT + 1)
+        );
+    }
+}
@@ -0,0 +1,52 @@
+//! Distinct id types for the objects of a world.
+//!
+//! All ids serialize as plain numbers, so world and save files keep their layout.
+
+use serde::{Deserialize, Serialize};
+use std::fmt::Display;
+
+macro_rules! id_type {
+    ($(#[$meta:meta])* $name:ident) => {
+        $(#[$meta])*
+        #[derive(
+            Debug, Clone, Copy, Serialize, Deserialize, Hash, Eq, PartialEq, Ord, PartialOrd,
+        )]
+        #[serde(transparent)]
+        pub struct $name(pub usize);
+
+        impl Display for $name {
+            fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+                write!(f, "{}", self.0)
+            }
+        }
+    };
+}
+
+id_type!(
+    /// Index of a room in `World::rooms`.
+    RoomId
+);
+id_type!(
+    /// Key of an entity in `World::entities`.
+    EntityId
+);
+id_type!(
+    /// Key of an actor in `World::actors`.
+    ActorId
+);
+id_type!(
+    /// Index of an event in `World::events`.
+    EventId
+);
+id_type!(
+    /// Index of a door in `World::doors`.
+    DoorId
+);
+
+/// Something the player can refer to in a command.
+#[derive(Debug, Clone, Copy, Hash, Eq, PartialEq)]
+pub enum Object {
+    Entity(EntityId),
+    Actor(ActorId),
+    Door(DoorId),
+}
@@ -1,13 +1,29 @@
+use crate::id::RoomId;
+
 mod actor;
 pub mod command;
 mod condition;
 mod direction;
+pub mod door;
+pub mod dot;
+pub mod dsl;
 mod entity;
 mod event;
+pub mod history;
+pub mod id;
+pub mod locale;
+pub mod map;
 pub mod parser;
+pub mod progress;
 mod room;
+pub mod save;
+pub mod script;
+pub mod source;
 pub mod state;
+pub mod validate;
+pub mod verb;
 pub mod victory;
+pub mod world;
 
 // We use u32 MAX to be safe on wasm32
-pub const GAME_OVER: usize = u32::MAX as usize;
+pub const GAME_OVER: RoomId = RoomId(u32::MAX as usize);
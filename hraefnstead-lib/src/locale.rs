@@ -0,0 +1,132 @@
+//! Languages the engine speaks.
+//!
+//! Every locale has a catalog, a JSON file in `locales/` holding
+//!
+//! * `messages`: the texts of the engine by id, with `{name}` placeholders
+//!   for the parts filled in while playing, e.g. `"There is no {thing} here."`
+//! * `verbs`: the words of each verb by its name, the first one shown in
+//!   the help, and its `help` text
+//! * `directions`: the words of each direction, and the grammar words
+//!   `fillers`, `separators`, `quantifiers`, `exceptions`, `conjunctions`,
+//!   `prepositions` of the verbs taking two objects and `pronouns`
+//!
+//! Anything missing from a catalog is taken from the English one. The
+//! locale of a game is the one of its world unless the player picked
+//! another, which is kept in the save.
+
+use serde::{Deserialize, Serialize};
+
+use std::collections::HashMap;
+use std::fmt::Display;
+use std::str::FromStr;
+use std::sync::OnceLock;
+
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, Hash, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Locale {
+    #[default]
+    En,
+    De,
+}
+
+/// The texts and words of a locale.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct Catalog {
+    pub messages: HashMap<String, String>,
+    pub verbs: HashMap<String, VerbWords>,
+    pub directions: HashMap<String, Vec<String>>,
+    pub fillers: Vec<String>,
+    pub separators: Vec<String>,
+    pub quantifiers: Vec<String>,
+    pub exceptions: Vec<String>,
+    pub conjunctions: Vec<String>,
+    pub prepositions: HashMap<String, Vec<String>>,
+    pub pronouns: HashMap<String, Vec<String>>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct VerbWords {
+    pub words: Vec<String>,
+    pub help: String,
+}
+
+impl Locale {
+    pub const ALL: [Locale; 2] = [Locale::En, Locale::De];
+
+    /// The catalog of the locale, read when first needed.
+    pub fn catalog(self) -> &'static Catalog {
+        static EN: OnceLock<Catalog> = OnceLock::new();
+        static DE: OnceLock<Catalog> = OnceLock::new();
+        let (cell, json) = match self {
+            Locale::En => (&EN, include_str!("../locales/en.json")),
+            Locale::De => (&DE, include_str!("../locales/de.json")),
+        };
+        cell.get_or_init(|| {
+            serde_json::from_str(json).expect("bundled message catalog must be valid")
+        })
+    }
+
+    /// The message with the given id, the id itself if no catalog has it.
+    pub fn message(self, id: &str) -> String {
+        [self, Locale::En]
+            .into_iter()
+            .find_map(|locale| locale.catalog().messages.get(id))
+            .map_or_else(|| id.to_string(), Clone::clone)
+    }
+
+    /// The message with its placeholders replaced by the arguments.
+    pub fn format(self, id: &str, args: &[(&str, &str)]) -> String {
+        let mut message = self.message(id);
+        for (name, value) in args {
+            message = message.replace(&format!("{{{name}}}"), value);
+        }
+        message
+    }
+}
+
+impl FromStr for Locale {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Locale::ALL
+            .into_iter()
+            .find(|locale| locale.to_string() == s.to_lowercase())
+            .ok_or_else(|| format!("unknown language \"{s}\", choose one of \"en\" or \"de\""))
+    }
+}
+
+impl Display for Locale {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Locale::En => write!(f, "en"),
+            Locale::De => write!(f, "de"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn catalogs_translate_every_english_message() {
+        let english = Locale::En.catalog();
+        for locale in Locale::ALL {
+            let catalog = locale.catalog();
+            for id in english.messages.keys() {
+                assert!(catalog.messages.contains_key(id), "{locale} lacks {id}");
+            }
+            for verb in english.verbs.keys() {
+                assert!(catalog.verbs.contains_key(verb), "{locale} lacks {verb}");
+            }
+        }
+        assert_eq!(
+            Locale::De.format("not_here", &[("thing", "Schwert")]),
+            "Hier gibt es kein Schwert."
+        );
+        assert_eq!(Locale::De.message("unknown"), "unknown");
+        assert_eq!("DE".parse(), Ok(Locale::De));
+    }
+}
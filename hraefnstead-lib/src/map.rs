@@ -0,0 +1,147 @@
+//! The map of the rooms the player has visited.
+//!
+//! Rooms are laid out on a grid by following the exits from the current
+//! room, every exit leading one cell into its direction, diagonal ones
+//! drawn as `/` and `\`. Unvisited rooms next to visited ones are shown as
+//! `?`. Rooms that do not fit the grid, e.g. because exits lead around a
+//! corner into an occupied cell, or up, down, in, out or through named exits,
+//! are left out.
+
+use crate::direction::Direction;
+use crate::id::RoomId;
+use crate::state::State;
+
+use std::collections::{HashMap, HashSet, VecDeque};
+
+/// The width of a room name on the map.
+const WIDTH: usize = 10;
+
+type Position = (i32, i32);
+
+/// Where an exit leads on the grid, `None` if it does not lead across it.
+fn offset(direction: &Direction) -> Option<Position> {
+    match direction {
+        Direction::North => Some((0, -1)),
+        Direction::South => Some((0, 1)),
+        Direction::East => Some((1, 0)),
+        Direction::West => Some((-1, 0)),
+        Direction::NorthEast => Some((1, -1)),
+        Direction::NorthWest => Some((-1, -1)),
+        Direction::SouthEast => Some((1, 1)),
+        Direction::SouthWest => Some((-1, 1)),
+        _ => None,
+    }
+}
+
+/// Draw the map around the current room.
+pub fn render_map(state: &State) -> String {
+    if state.is_dead() {
+        return String::new();
+    }
+    let start = state.get_progress().loc;
+    let mut rooms: HashMap<Position, RoomId> = HashMap::from([((0, 0), start)]);
+    let mut positions: HashMap<RoomId, Position> = HashMap::from([(start, (0, 0))]);
+    let mut connections: HashSet<(Position, Position)> = HashSet::new();
+    let mut queue = VecDeque::from([start]);
+    while let Some(room) = queue.pop_front() {
+        if !state.has_visited(room) {
+            continue;
+        }
+        let (x, y) = positions[&room];
+        let mut exits: Vec<_> = state.get_exits_of(room).into_iter().collect();
+        exits.sort();
+        for (dir, target) in exits {
+            let Some((dx, dy)) = offset(&dir) else {
+                continue;
+            };
+            let position = (x + dx, y + dy);
+            if !positions.contains_key(&target) && !rooms.contains_key(&position) {
+                rooms.insert(position, target);
+                positions.insert(target, position);
+                queue.push_back(target);
+            }
+            if positions[&target] == position {
+                connections.insert(((x, y).min(position), (x, y).max(position)));
+            }
+        }
+    }
+
+    let min_x = rooms.keys().map(|(x, _)| *x).min().unwrap_or_default();
+    let max_x = rooms.keys().map(|(x, _)| *x).max().unwrap_or_default();
+    let min_y = rooms.keys().map(|(_, y)| *y).min().unwrap_or_default();
+    let max_y = rooms.keys().map(|(_, y)| *y).max().unwrap_or_default();
+    let mut lines = Vec::new();
+    for y in min_y..=max_y {
+        let mut room_line = String::new();
+        let mut link_line = String::new();
+        for x in min_x..=max_x {
+            room_line.push_str(&match rooms.get(&(x, y)) {
+                Some(room) if *room == start => format!("*{:^WIDTH$}*", name(state, *room)),
+                Some(room) if state.has_visited(*room) => {
+                    format!("[{:^WIDTH$}]", name(state, *room))
+                }
+                Some(_) => format!("[{:^WIDTH$}]", "?"),
+                None => " ".repeat(WIDTH + 2),
+            });
+            let below = if connections.contains(&((x, y), (x, y + 1))) {
+                "|"
+            } else {
+                ""
+            };
+            link_line.push_str(&format!("{below:^0$}", WIDTH + 2));
+            if x < max_x {
+                let right = connections.contains(&((x, y), (x + 1, y)));
+                room_line.push_str(if right { "---" } else { "   " });
+                let falling = connections.contains(&((x, y), (x + 1, y + 1)));
+                let rising = connections.contains(&((x, y + 1), (x + 1, y)));
+                link_line.push_str(match (falling, rising) {
+                    (true, true) => " X ",
+                    (true, false) => " \\ ",
+                    (false, true) => " / ",
+                    (false, false) => "   ",
+                });
+            }
+        }
+        lines.push(room_line.trim_end().to_string());
+        if y < max_y {
+            lines.push(link_line.trim_end().to_string());
+        }
+    }
+    format!(
+        "\n{}\n\n{}",
+        lines.join("\n"),
+        state.message("map_legend", &[])
+    )
+}
+
+/// The name of a room, cut to fit the map.
+fn name(state: &State, room: RoomId) -> String {
+    state.get_world().rooms[room.0]
+        .name
+        .chars()
+        .take(WIDTH)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn shows_visited_rooms_and_their_neighbours() {
+        let mut state = State::new();
+        state.set_location(RoomId(1));
+        state.set_location(RoomId(2));
+        let map = render_map(&state);
+        let lines: Vec<&str> = map.lines().collect();
+        assert_eq!(
+            lines[1..4],
+            ["[ Corridor ]---* Chamber  *", "     |", "[ Entrance ]"]
+        );
+
+        state.set_location(RoomId(0));
+        let map = render_map(&state);
+        assert_eq!(map.lines().nth(3), Some("* Entrance *"));
+        assert!(!map.contains('?'));
+    }
+}
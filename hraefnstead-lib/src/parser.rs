@@ -1,120 +1,600 @@
 use crate::command::Command;
 use crate::direction::Direction;
-use crate::state::State;
-
-pub fn parse(input: &str, state: &mut State) -> Command {
-    let mut tokens = input.split_whitespace();
-    let command = tokens.next().unwrap();
-    if state.is_dead() {
-        state.log("\nYou can't do that, you are still dead!");
-        return Command::None;
-    }
-    match command {
-        "look" => Command::Look,
-        "quit" => {
-            state.log("Goodbye!");
-            Command::Quit
-        }
-        "save" => {
-            state.log("Saving game...");
-            let file_name = if let Some(file_name) = tokens.next() {
-                file_name.to_string()
-            } else {
-                String::new()
-            };
-            Command::Save(file_name)
+use crate::verb::{Arguments, Verb, Vocabulary};
+
+use std::collections::HashSet;
+use std::ops::Range;
+
+/// Bring input into the form the parser expects: lower case words without
+/// punctuation and the filler words of the vocabulary, separated by single
+/// spaces.
+pub fn normalize(input: &str, vocabulary: &Vocabulary) -> String {
+    words(input, vocabulary.fillers())
+        .into_iter()
+        .map(|(word, _)| word)
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// The normalized words of the input with where they are in it.
+fn words(input: &str, fillers: &[String]) -> Vec<(String, Span)> {
+    let mut words = Vec::new();
+    let mut word = String::new();
+    let mut start = 0;
+    for (i, c) in input.char_indices().chain([(input.len(), ' ')]) {
+        if c == '\'' {
+            continue;
         }
-        "load" => {
-            state.log("Loading game...");
-            let file_name = if let Some(file_name) = tokens.next() {
-                file_name.to_string()
-            } else {
-                String::new()
-            };
-            Command::Load(file_name)
-        }
-        "go" => {
-            if let Some(dir) = tokens.next() {
-                if let Some(direction) = Direction::from_str(dir) {
-                    Command::Move(direction)
-                } else {
-                    state.log("I don't know that direction.");
-                    Command::None
-                }
-            } else {
-                state.log("You need to specify a direction to go to.");
-                Command::None
+        if c.is_alphanumeric() {
+            if word.is_empty() {
+                start = i;
             }
+            word.extend(c.to_lowercase());
+        } else if !word.is_empty() {
+            words.push((std::mem::take(&mut word), start..i));
         }
-        "north" | "n" => Command::Move(Direction::North),
-        "south" | "s" => Command::Move(Direction::South),
-        "east" | "e" => Command::Move(Direction::East),
-        "west" | "w" => Command::Move(Direction::West),
-        "take" | "t" => {
-            if let Some(thing) = tokens.next() {
-                Command::Take(thing.to_string())
-            } else {
-                state.log("You need to specify an item to take.");
-                Command::None
-            }
+    }
+    words.retain(|(word, _)| !fillers.contains(word));
+    words
+}
+
+/// Split a line of input into the commands it holds. Commands are separated
+/// by commas, semicolons, periods that end a word, and words like "then".
+pub fn split_commands(input: &str, vocabulary: &Vocabulary) -> Vec<String> {
+    let mut parts = Vec::new();
+    let mut part = String::new();
+    let mut chars = input.chars().peekable();
+    while let Some(c) = chars.next() {
+        let ends_part = match c {
+            ',' | ';' => true,
+            // Periods inside a word, e.g. of a file name, do not separate.
+            '.' => chars.peek().is_none_or(|next| next.is_whitespace()),
+            _ => false,
+        };
+        if ends_part {
+            parts.push(std::mem::take(&mut part));
+        } else {
+            part.push(c);
         }
-        "drop" => {
-            if let Some(thing) = tokens.next() {
-                Command::Drop(thing.to_string())
+    }
+    parts.push(part);
+
+    let mut commands = Vec::new();
+    for part in parts {
+        let mut words = Vec::new();
+        for word in part.split_whitespace() {
+            if vocabulary
+                .separators()
+                .contains(&normalize(word, vocabulary))
+            {
+                commands.push(words.join(" "));
+                words.clear();
             } else {
-                state.log("You need to specify an item to drop.");
-                Command::None
+                words.push(word);
             }
         }
-        "inventory" | "inv" | "i" => Command::Inventory,
-        "examine" => {
-            if let Some(thing) = tokens.next() {
-                Command::Examine(thing.to_string())
-            } else {
-                state.log("You need to specify an item to examine.");
-                Command::None
-            }
+        commands.push(words.join(" "));
+    }
+    commands.retain(|command| !normalize(command, vocabulary).is_empty());
+    commands
+}
+
+/// A range of bytes of the input.
+pub type Span = Range<usize>;
+
+/// A command understood from the player's input.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParsedCommand {
+    pub command: Command,
+    /// Where the verb is in the input.
+    pub verb: Span,
+    /// The verb as the player typed it and the verb it was taken for, if it
+    /// was misspelled.
+    pub correction: Option<(String, String)>,
+}
+
+/// Why input was not understood, and which part of it is at fault.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParseError {
+    pub kind: ParseErrorKind,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ParseErrorKind {
+    /// The input holds no words.
+    EmptyInput,
+    /// The verb is unknown. The known verbs close to it are suggested.
+    UnknownVerb(Vec<String>),
+    /// The verb needs an object.
+    MissingObject(String),
+    /// The verb needs a second object joined by a preposition.
+    MissingIndirectObject(String),
+    /// "go" without a direction.
+    MissingDirection,
+    /// "go" towards something that is no direction.
+    UnknownDirection(String),
+}
+
+/// Parse a single command with the verbs of the vocabulary. Only the
+/// syntax is checked, whether the objects exist is up to the game.
+pub fn parse(input: &str, vocabulary: &Vocabulary) -> Result<ParsedCommand, ParseError> {
+    let words = words(input, vocabulary.fillers());
+    let Some((typed, span)) = words.first() else {
+        return Err(ParseError {
+            kind: ParseErrorKind::EmptyInput,
+            span: 0..input.len(),
+        });
+    };
+    let suggestions = closest(typed, vocabulary.words());
+    let found = vocabulary.find(typed).map(|verb| (verb, None)).or_else(|| {
+        let [correction] = suggestions.as_slice() else {
+            return None;
+        };
+        let verb = vocabulary.find(correction)?;
+        Some((verb, Some((typed.clone(), correction.to_string()))))
+    });
+    let Some((verb, correction)) = found else {
+        // A direction or named exit on its own, like "door", goes there.
+        let phrase = noun_phrase(words.iter().map(|(word, _)| word.as_str()));
+        if let Some(direction) = phrase.and_then(|phrase| vocabulary.direction(&phrase)) {
+            return Ok(ParsedCommand {
+                command: Command::Move(direction),
+                verb: span.clone(),
+                correction: None,
+            });
         }
-        "use" => {
-            if let Some(thing) = tokens.next() {
-                Command::Use(thing.to_string())
+        return Err(ParseError {
+            kind: ParseErrorKind::UnknownVerb(
+                suggestions.iter().map(ToString::to_string).collect(),
+            ),
+            span: span.clone(),
+        });
+    };
+    Ok(ParsedCommand {
+        command: verb_command(verb, &words, input, vocabulary)?,
+        verb: span.clone(),
+        correction,
+    })
+}
+
+/// The command for a verb and the words following it.
+fn verb_command(
+    verb: &Verb,
+    words: &[(String, Span)],
+    input: &str,
+    vocabulary: &Vocabulary,
+) -> Result<Command, ParseError> {
+    let name = verb.name.as_str();
+    let verb_end = words[0].1.end;
+    let rest = &words[1..];
+    let phrase = noun_phrase(rest.iter().map(|(word, _)| word.as_str()));
+    let missing_object = || ParseError {
+        kind: ParseErrorKind::MissingObject(name.to_string()),
+        span: verb_end..input.len(),
+    };
+    let command = match verb.arguments {
+        Arguments::Nothing => match name {
+            "look" => Command::Look,
+            "quit" => Command::Quit,
+            "inventory" => Command::Inventory,
+            "map" => Command::Map,
+            "again" => Command::Again,
+            "history" => Command::History,
+            direction => Command::Move(Direction::from_str(direction).ok_or(ParseError {
+                kind: ParseErrorKind::UnknownVerb(Vec::new()),
+                span: words[0].1.clone(),
+            })?),
+        },
+        Arguments::FileName => {
+            // File names keep their case and punctuation.
+            let file_name = input[verb_end..]
+                .split_whitespace()
+                .next()
+                .unwrap_or_default()
+                .to_string();
+            if name == "load" {
+                Command::Load(file_name)
             } else {
-                state.log("You need to specify an item to use.");
-                Command::None
+                Command::Save(file_name)
             }
         }
-        "attack" => {
-            if let Some(thing) = tokens.next() {
-                Command::Attack(thing.to_string())
-            } else {
-                state.log("You need to specify an enemy to atack.");
-                Command::None
+        Arguments::Direction => {
+            let (Some(dir), Some((_, first)), Some((_, last))) =
+                (phrase, rest.first(), rest.last())
+            else {
+                return Err(ParseError {
+                    kind: ParseErrorKind::MissingDirection,
+                    span: verb_end..input.len(),
+                });
+            };
+            let Some(direction) = vocabulary.direction(&dir) else {
+                return Err(ParseError {
+                    kind: ParseErrorKind::UnknownDirection(dir),
+                    span: first.start..last.end,
+                });
+            };
+            Command::Move(direction)
+        }
+        Arguments::Object => {
+            let thing = phrase.ok_or_else(missing_object)?;
+            let is_help = vocabulary
+                .find(&thing)
+                .is_some_and(|verb| verb.name == "help");
+            match (name, quantifier(&thing, vocabulary)) {
+                ("take", Some(except)) => Command::TakeAll(except),
+                ("drop", Some(except)) => Command::DropAll(except),
+                ("take", None) => Command::Take(thing),
+                ("drop", None) => Command::Drop(thing),
+                ("craft", _) if is_help => Command::CraftHelp,
+                ("craft", _) => Command::Craft(thing),
+                ("open", _) => Command::Open(thing),
+                ("close", _) => Command::Close(thing),
+                _ => Command::Examine(thing),
             }
         }
-        "help" => {
-            if let Some(thing) = tokens.next() {
-                Command::Help(thing.to_string())
-            } else {
-                Command::Help("Default".to_string())
+        Arguments::Objects => {
+            let thing = phrase.ok_or_else(missing_object)?;
+            objects_command(name, &thing, vocabulary.prepositions(name)).unwrap_or(match name {
+                "attack" => Command::Attack(thing),
+                "lock" => Command::Lock(thing),
+                "unlock" => Command::Unlock(thing),
+                _ => Command::Use(thing),
+            })
+        }
+        Arguments::TwoObjects => {
+            let thing = phrase.ok_or_else(missing_object)?;
+            objects_command(name, &thing, vocabulary.prepositions(name)).ok_or(ParseError {
+                kind: ParseErrorKind::MissingIndirectObject(name.to_string()),
+                span: verb_end..input.len(),
+            })?
+        }
+        Arguments::Topic => Command::Help(
+            rest.first()
+                .map_or("Default".to_string(), |(word, _)| word.clone()),
+        ),
+    };
+    Ok(command)
+}
+
+/// The candidates closest to a word by edit distance, if close enough to be
+/// a typo: one edit for words up to six letters, two for longer ones.
+pub fn closest<'a>(word: &str, candidates: impl IntoIterator<Item = &'a str>) -> Vec<&'a str> {
+    let tolerance = match word.chars().count() {
+        0..=3 => 0,
+        4..=6 => 1,
+        _ => 2,
+    };
+    let mut best = Vec::new();
+    let mut best_distance = tolerance;
+    for candidate in candidates {
+        let distance = edit_distance(word, candidate);
+        if distance == 0 || distance > best_distance {
+            continue;
+        }
+        if distance < best_distance {
+            best.clear();
+            best_distance = distance;
+        }
+        if !best.contains(&candidate) {
+            best.push(candidate);
+        }
+    }
+    best
+}
+
+/// The number of insertions, deletions, substitutions and swaps of
+/// neighbouring letters that turn one word into the other.
+pub fn edit_distance(a: &str, b: &str) -> usize {
+    let a: Vec<char> = a.chars().collect();
+    let b: Vec<char> = b.chars().collect();
+    let mut rows = vec![vec![0; b.len() + 1]; a.len() + 1];
+    for (i, row) in rows.iter_mut().enumerate() {
+        row[0] = i;
+    }
+    rows[0] = (0..=b.len()).collect();
+    for i in 1..=a.len() {
+        for j in 1..=b.len() {
+            let cost = usize::from(a[i - 1] != b[j - 1]);
+            let mut distance = (rows[i - 1][j] + 1)
+                .min(rows[i][j - 1] + 1)
+                .min(rows[i - 1][j - 1] + cost);
+            if i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+                distance = distance.min(rows[i - 2][j - 2] + 1);
             }
+            rows[i][j] = distance;
         }
+    }
+    rows[a.len()][b.len()]
+}
+
+/// Quoted words joined like "a", "b" or "c", with the given word for "or".
+pub(crate) fn alternatives(words: &[&str], or: &str) -> String {
+    let mut quoted: Vec<String> = words.iter().map(|word| format!("\"{word}\"")).collect();
+    let last = quoted.pop().unwrap_or_default();
+    if quoted.is_empty() {
+        last
+    } else {
+        format!("{} {or} {last}", quoted.join(", "))
+    }
+}
+
+/// The rest of the input as one phrase, `None` if nothing is left.
+fn noun_phrase<'a>(tokens: impl Iterator<Item = &'a str>) -> Option<String> {
+    let phrase = tokens.collect::<Vec<_>>().join(" ");
+    if phrase.is_empty() {
+        None
+    } else {
+        Some(phrase)
+    }
+}
 
-        "craft" => {
-            if let Some(thing) = tokens.next() {
-                thing.to_string();
-                if thing.contains("help") {
-                    Command::CraftHelp
-                } else {
-                    Command::Craft(thing.to_string())
-                }
+/// The objects excepted from a phrase like "all" or "everything but coin and
+/// chips"; None if the phrase does not start with such a quantifier.
+fn quantifier(phrase: &str, vocabulary: &Vocabulary) -> Option<Vec<String>> {
+    let is = |words: &[String], word: &str| words.iter().any(|known| known == word);
+    let mut words = phrase.split_whitespace();
+    if !is(vocabulary.quantifiers(), words.next()?) {
+        return None;
+    }
+    match words.next() {
+        None => Some(Vec::new()),
+        Some(word) if is(vocabulary.exceptions(), word) => {
+            let words: Vec<&str> = words.collect();
+            let except: Vec<String> = words
+                .split(|word| is(vocabulary.conjunctions(), word))
+                .filter(|thing| !thing.is_empty())
+                .map(|thing| thing.join(" "))
+                .collect();
+            (!except.is_empty()).then_some(except)
+        }
+        Some(_) => None,
+    }
+}
+
+/// The command for a verb and a phrase naming two objects joined by an
+/// English preposition, like "use coin on machine" or "give chips to goblin".
+pub fn two_object_command(verb: &str, phrase: &str) -> Option<Command> {
+    objects_command(verb, phrase, Vocabulary::default().prepositions(verb))
+}
+
+/// The command for a verb and a phrase naming two objects joined by one of
+/// the prepositions.
+fn objects_command(verb: &str, phrase: &str, prepositions: &[String]) -> Option<Command> {
+    if !matches!(verb, "use" | "give" | "put" | "attack" | "lock" | "unlock") {
+        return None;
+    }
+    let words: Vec<&str> = phrase.split_whitespace().collect();
+    let split = words
+        .iter()
+        .position(|word| prepositions.iter().any(|known| known == word))?;
+    if split == 0 || split + 1 == words.len() {
+        return None;
+    }
+    let object = words[..split].join(" ");
+    let other = words[split + 1..].join(" ");
+    Some(match verb {
+        "use" => Command::UseOn(object, other),
+        "give" => Command::Give(object, other),
+        "put" => Command::PutIn(object, other),
+        "lock" => Command::LockWith(object, other),
+        "unlock" => Command::UnlockWith(object, other),
+        _ => Command::AttackWith(object, other),
+    })
+}
+
+/// The number of words of the longest alias, or name without its article,
+/// found in the phrase; 0 if none is found.
+pub fn match_length(phrase: &str, name: &str, aliases: &HashSet<String>) -> usize {
+    let phrase: Vec<String> = phrase.split_whitespace().map(str::to_lowercase).collect();
+    let name = name.to_lowercase();
+    let name = without_article(&name);
+    aliases
+        .iter()
+        .map(String::as_str)
+        .chain([name])
+        .map(|candidate| {
+            let candidate: Vec<String> = candidate
+                .split_whitespace()
+                .map(str::to_lowercase)
+                .collect();
+            let found = !candidate.is_empty()
+                && phrase
+                    .windows(candidate.len())
+                    .any(|words| words == candidate.as_slice());
+            if found {
+                candidate.len()
             } else {
-                state.log("You cant craft with that");
-                Command::None
+                0
             }
+        })
+        .max()
+        .unwrap_or(0)
+}
+
+/// A lower case name without its leading article.
+pub fn without_article(name: &str) -> &str {
+    ["a ", "an ", "the "]
+        .iter()
+        .find_map(|article| name.strip_prefix(article))
+        .unwrap_or(name)
+}
+
+/// The candidate with the longest match, `None` if nothing matches or
+/// several candidates match equally well.
+pub fn best_match<T: PartialEq>(candidates: impl IntoIterator<Item = (T, usize)>) -> Option<T> {
+    let mut best = best_matches(candidates);
+    if best.len() == 1 {
+        best.pop()
+    } else {
+        None
+    }
+}
+
+/// All different candidates sharing the longest match.
+pub fn best_matches<T: PartialEq>(candidates: impl IntoIterator<Item = (T, usize)>) -> Vec<T> {
+    let mut best = Vec::new();
+    let mut best_length = 0;
+    for (candidate, length) in candidates {
+        if length == 0 || length < best_length {
+            continue;
         }
-        _ => {
-            state.log("I don't understand that command.");
-            Command::None
+        if length > best_length {
+            best.clear();
+            best_length = length;
         }
+        if !best.contains(&candidate) {
+            best.push(candidate);
+        }
+    }
+    best
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::locale::Locale;
+
+    /// The command parsed from the input, panicking on errors.
+    fn command(input: &str) -> Command {
+        parse(input, &Vocabulary::default()).unwrap().command
+    }
+
+    /// The kind of error parsing the input fails with.
+    fn error(input: &str) -> ParseErrorKind {
+        parse(input, &Vocabulary::default()).unwrap_err().kind
+    }
+
+    #[test]
+    fn input_is_normalized() {
+        let vocabulary = Vocabulary::default();
+        assert_eq!(
+            normalize("  Take the COIN, please! ", &vocabulary),
+            "take coin"
+        );
+        assert_eq!(
+            normalize("Examine the goblin's bed.", &vocabulary),
+            "examine goblins bed"
+        );
+        assert_eq!(error(""), ParseErrorKind::EmptyInput);
+        assert_eq!(error(" ?! "), ParseErrorKind::EmptyInput);
+        assert_eq!(command("Look."), Command::Look);
+        assert_eq!(
+            command("save My-Game.json"),
+            Command::Save("My-Game.json".to_string())
+        );
+        assert_eq!(command("help"), Command::Help("Default".to_string()));
+    }
+
+    #[test]
+    fn errors_point_at_the_input() {
+        assert_eq!(
+            parse("  go  sideways", &Vocabulary::default()),
+            Err(ParseError {
+                kind: ParseErrorKind::UnknownDirection("sideways".to_string()),
+                span: 6..14,
+            })
+        );
+        assert_eq!(
+            parse("Take the", &Vocabulary::default()),
+            Err(ParseError {
+                kind: ParseErrorKind::MissingObject("take".to_string()),
+                span: 4..8,
+            })
+        );
+        assert_eq!(error("go"), ParseErrorKind::MissingDirection);
+        assert_eq!(error("dance"), ParseErrorKind::UnknownVerb(vec![]));
+    }
+
+    #[test]
+    fn input_is_split_into_commands() {
+        let vocabulary = Vocabulary::default();
+        assert_eq!(
+            split_commands("take coin, go north. Look then save my.game.", &vocabulary),
+            ["take coin", "go north", "Look", "save my.game"]
+        );
+        assert!(split_commands(" . , then", &vocabulary).is_empty());
+    }
+
+    #[test]
+    fn typos_are_corrected() {
+        assert_eq!(edit_distance("tkae", "take"), 1);
+        assert_eq!(edit_distance("examine", "exmaine"), 1);
+        assert_eq!(edit_distance("coin", "chips"), 3);
+        let vocabulary = Vocabulary::default();
+        assert_eq!(closest("lok", vocabulary.words()), Vec::<&str>::new());
+        assert_eq!(closest("loook", vocabulary.words()), ["look"]);
+
+        assert_eq!(
+            parse("the tkae coin", &Vocabulary::default()),
+            Ok(ParsedCommand {
+                command: Command::Take("coin".to_string()),
+                verb: 4..8,
+                correction: Some(("tkae".to_string(), "take".to_string())),
+            })
+        );
+        assert_eq!(
+            error("loak"),
+            ParseErrorKind::UnknownVerb(vec![
+                "look".to_string(),
+                "load".to_string(),
+                "lock".to_string()
+            ])
+        );
+    }
+
+    #[test]
+    fn prepositions_separate_two_objects() {
+        assert_eq!(
+            command("use the coin on the vending machine"),
+            Command::UseOn("coin".to_string(), "vending machine".to_string())
+        );
+        assert_eq!(
+            command("put coin into slot"),
+            Command::PutIn("coin".to_string(), "slot".to_string())
+        );
+        assert_eq!(
+            command("attack goblin with golden armor"),
+            Command::AttackWith("goblin".to_string(), "golden armor".to_string())
+        );
+        assert_eq!(command("use coin"), Command::Use("coin".to_string()));
+        assert_eq!(
+            error("give"),
+            ParseErrorKind::MissingObject("give".to_string())
+        );
+        assert_eq!(
+            error("give chips"),
+            ParseErrorKind::MissingIndirectObject("give".to_string())
+        );
+        assert_eq!(command("take all"), Command::TakeAll(vec![]));
+        assert_eq!(
+            command("drop everything except the coin and golden armor"),
+            Command::DropAll(vec!["coin".to_string(), "golden armor".to_string()])
+        );
+    }
+
+    #[test]
+    fn locales_have_their_own_words() {
+        let german = Vocabulary::new(Locale::De);
+        let command = |input| parse(input, &german).unwrap().command;
+        assert_eq!(
+            split_commands("nimm die Münze, dann geh nach Norden", &german),
+            ["nimm die Münze", "geh nach Norden"]
+        );
+        assert_eq!(
+            command("Nimm die Münze"),
+            Command::Take("münze".to_string())
+        );
+        assert_eq!(command("geh nach Norden"), Command::Move(Direction::North));
+        assert_eq!(
+            command("gib die Chips an den Goblin"),
+            Command::Give("chips".to_string(), "goblin".to_string())
+        );
+        assert_eq!(
+            command("lege alles außer Münze und Chips"),
+            Command::DropAll(vec!["münze".to_string(), "chips".to_string()])
+        );
+        assert_eq!(command("stelle hilfe"), Command::CraftHelp);
+        assert!(parse("take coin", &german).is_err());
     }
 }
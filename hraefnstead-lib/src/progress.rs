@@ -0,0 +1,61 @@
+use crate::direction::Direction;
+use crate::door::DoorState;
+use crate::id::{ActorId, DoorId, EntityId, EventId, RoomId};
+use crate::locale::Locale;
+use crate::world::World;
+
+use std::collections::{HashMap, HashSet};
+
+use serde::{Deserialize, Serialize};
+
+/// The part of the game state that changes while playing.
+///
+/// This is all that ends up in a save file. Room contents are only stored
+/// for rooms that differ from the world definition, so fixes to the world
+/// also reach games that were saved before.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub struct Progress {
+    pub loc: RoomId,
+    pub inventory: HashSet<EntityId>,
+    pub room_entities: HashMap<RoomId, HashSet<EntityId>>,
+    pub room_actors: HashMap<RoomId, HashSet<ActorId>>,
+    /// Exits opened during the game in addition to the ones of the world.
+    pub added_exits: HashMap<RoomId, HashMap<Direction, RoomId>>,
+    pub active_events: HashSet<EventId>,
+    #[serde(default)]
+    pub flags: HashSet<String>,
+    #[serde(default)]
+    pub counters: HashMap<String, i64>,
+    /// Room descriptions replaced by event scripts.
+    #[serde(default)]
+    pub room_descriptions: HashMap<RoomId, String>,
+    /// The rooms the player has been in, for the map.
+    #[serde(default)]
+    pub visited: HashSet<RoomId>,
+    /// Doors opened, closed, locked or unlocked during the game.
+    #[serde(default)]
+    pub doors: HashMap<DoorId, DoorState>,
+    /// The locale the player picked instead of the one of the world.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub locale: Option<Locale>,
+}
+
+impl Progress {
+    /// The progress at the very beginning of a game in the given world.
+    pub fn new(world: &World) -> Self {
+        Self {
+            loc: world.start,
+            inventory: HashSet::new(),
+            room_entities: HashMap::new(),
+            room_actors: HashMap::new(),
+            added_exits: HashMap::new(),
+            active_events: world.active_events.clone(),
+            flags: HashSet::new(),
+            counters: HashMap::new(),
+            room_descriptions: HashMap::new(),
+            visited: HashSet::from([world.start]),
+            doors: HashMap::new(),
+            locale: None,
+        }
+    }
+}
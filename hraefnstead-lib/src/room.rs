@@ -1,4 +1,5 @@
 use crate::direction::Direction;
+use crate::id::{ActorId, EntityId, RoomId};
 use serde::{Deserialize, Serialize};
 use std::collections::{HashMap, HashSet};
 
@@ -6,18 +7,18 @@ use std::collections::{HashMap, HashSet};
 pub struct Room {
     pub name: String,
     pub description: String,
-    pub entities: HashSet<usize>,
-    pub actors: HashSet<usize>,
-    pub exits: HashMap<Direction, usize>,
+    pub entities: HashSet<EntityId>,
+    pub actors: HashSet<ActorId>,
+    pub exits: HashMap<Direction, RoomId>,
 }
 
 impl Room {
     pub fn new(
         name: &str,
         description: &str,
-        entities: HashSet<usize>,
-        actors: HashSet<usize>,
-        exits: HashMap<Direction, usize>,
+        entities: HashSet<EntityId>,
+        actors: HashSet<ActorId>,
+        exits: HashMap<Direction, RoomId>,
     ) -> Self {
         Self {
             name: name.to_string(),
@@ -32,33 +33,33 @@ impl Room {
         &self.description
     }
 
-    pub fn get_exits(&self) -> &HashMap<Direction, usize> {
+    pub fn get_exits(&self) -> &HashMap<Direction, RoomId> {
         &self.exits
     }
 
-    pub fn get_exit(&self, dir: Direction) -> Option<usize> {
+    pub fn get_exit(&self, dir: Direction) -> Option<RoomId> {
         self.exits.get(&dir).copied()
     }
 
-    pub fn get_entities(&self) -> &HashSet<usize> {
+    pub fn get_entities(&self) -> &HashSet<EntityId> {
         &self.entities
     }
 
-    pub fn remove_entity(&mut self, entity_id: usize) -> bool {
+    pub fn remove_entity(&mut self, entity_id: EntityId) -> bool {
         self.entities.remove(&entity_id)
     }
 
-    pub fn add_entity(&mut self, entity_id: usize) {
+    pub fn add_entity(&mut self, entity_id: EntityId) {
         self.entities.insert(entity_id);
     }
 
-    pub fn get_actors(&self) -> &HashSet<usize> {
+    pub fn get_actors(&self) -> &HashSet<ActorId> {
         &self.actors
     }
-    pub fn remove_actor(&mut self, actor_id: usize) -> bool {
+    pub fn remove_actor(&mut self, actor_id: ActorId) -> bool {
         self.actors.remove(&actor_id)
     }
-    pub fn add_exit(&mut self, direction: Direction, room_number: usize) {
+    pub fn add_exit(&mut self, direction: Direction, room_number: RoomId) {
         self.exits.insert(direction, room_number);
     }
 }
@@ -0,0 +1,260 @@
+//! Save files and the migration of saves written by older versions.
+//!
+//! A save file is a JSON envelope around the [`Progress`] of a game that
+//! records the save format version, the world the game was played in and
+//! when it was saved. Saves written before the envelope existed are bare
+//! dumps of the game state and are treated as format version 0.
+//!
+//! A loaded progress is checked against the world before it is played, so a
+//! stale or edited save that refers to rooms, things or events the world
+//! does not have is rejected instead of crashing the game.
+
+use crate::direction::Direction;
+use crate::id::{ActorId, DoorId, EntityId, EventId, RoomId};
+use crate::progress::Progress;
+use crate::world::World;
+
+use std::collections::{HashMap, HashSet};
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use thiserror::Error;
+
+/// The format version written by [`SaveGame::new`].
+pub const SAVE_FORMAT_VERSION: u64 = 1;
+
+type Migration = fn(Value, &World) -> Result<Value, SaveError>;
+
+/// `MIGRATIONS[n]` upgrades a save of format version `n` to version `n + 1`.
+const MIGRATIONS: [Migration; 1] = [migrate_bare_dump];
+
+#[derive(Debug, Error)]
+pub enum SaveError {
+    #[error("Game file seems to be corrupt! ({0})")]
+    Corrupt(#[from] serde_json::Error),
+    #[error("Game file has format version {0}, but this game only supports up to version {SAVE_FORMAT_VERSION}.")]
+    TooNew(u64),
+    #[error("Game file belongs to the world '{found}', but the current world is '{expected}'.")]
+    WrongWorld { expected: String, found: String },
+    #[error("Game file refers to {kind} {id}, which the current world does not have.")]
+    UnknownId { kind: &'static str, id: usize },
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub struct SaveGame {
+    pub format_version: u64,
+    pub world_id: String,
+    /// Seconds since the unix epoch.
+    pub timestamp: u64,
+    pub progress: Progress,
+}
+
+impl SaveGame {
+    pub fn new(world: &World, progress: Progress, timestamp: u64) -> Self {
+        Self {
+            format_version: SAVE_FORMAT_VERSION,
+            world_id: world.id.clone(),
+            timestamp,
+            progress,
+        }
+    }
+
+    pub fn to_json(&self) -> Result<String, SaveError> {
+        Ok(serde_json::to_string(self)?)
+    }
+
+    /// Read a save file of any known format version for the given world.
+    pub fn from_json(save: &str, world: &World) -> Result<Self, SaveError> {
+        let mut save: Value = serde_json::from_str(save)?;
+        let mut version = save
+            .get("format_version")
+            .and_then(Value::as_u64)
+            .unwrap_or(0);
+        if version > SAVE_FORMAT_VERSION {
+            return Err(SaveError::TooNew(version));
+        }
+        while version < SAVE_FORMAT_VERSION {
+            save = MIGRATIONS[version as usize](save, world)?;
+            version += 1;
+        }
+        let save: SaveGame = serde_json::from_value(save)?;
+        if save.world_id != world.id {
+            return Err(SaveError::WrongWorld {
+                expected: world.id.clone(),
+                found: save.world_id,
+            });
+        }
+        check_ids(&save.progress, world)?;
+        Ok(save)
+    }
+}
+
+/// Fail with the first id of the progress that the world does not have.
+fn check_ids(progress: &Progress, world: &World) -> Result<(), SaveError> {
+    let unknown = |kind, id| Err(SaveError::UnknownId { kind, id });
+    let rooms = [progress.loc]
+        .into_iter()
+        .filter(|room| *room != crate::GAME_OVER)
+        .chain(progress.room_entities.keys().copied())
+        .chain(progress.room_actors.keys().copied())
+        .chain(progress.added_exits.keys().copied())
+        .chain(
+            progress
+                .added_exits
+                .values()
+                .flat_map(|exits| exits.values().copied()),
+        )
+        .chain(progress.room_descriptions.keys().copied())
+        .chain(progress.visited.iter().copied());
+    for RoomId(id) in rooms {
+        if id >= world.rooms.len() {
+            return unknown("room", id);
+        }
+    }
+    let entities = progress
+        .inventory
+        .iter()
+        .chain(progress.room_entities.values().flatten());
+    for id in entities {
+        if !world.entities.contains_key(id) {
+            return unknown("entity", id.0);
+        }
+    }
+    for id in progress.room_actors.values().flatten() {
+        if !world.actors.contains_key(id) {
+            return unknown("actor", id.0);
+        }
+    }
+    for EventId(id) in progress.active_events.iter().copied() {
+        if id >= world.events.len() {
+            return unknown("event", id);
+        }
+    }
+    for DoorId(id) in progress.doors.keys().copied() {
+        if id >= world.doors.len() {
+            return unknown("door", id);
+        }
+    }
+    Ok(())
+}
+
+/// Layout of the rooms in saves that contained the whole game state.
+#[derive(Deserialize)]
+struct LegacyRoom {
+    entities: HashSet<EntityId>,
+    actors: HashSet<ActorId>,
+    exits: HashMap<Direction, RoomId>,
+}
+
+/// Layout of saves that contained the whole game state including the world.
+#[derive(Deserialize)]
+struct LegacyState {
+    loc: RoomId,
+    inventory: HashSet<EntityId>,
+    rooms: Vec<LegacyRoom>,
+    active_events: HashSet<EventId>,
+}
+
+/// Version 0 saves are either a dump of the whole game state or a bare
+/// progress without any envelope. Neither says which world it belongs to,
+/// so they are assumed to belong to the current one.
+fn migrate_bare_dump(save: Value, world: &World) -> Result<Value, SaveError> {
+    let progress = if save.get("rooms").is_some() {
+        let legacy: LegacyState = serde_json::from_value(save)?;
+        let mut progress = Progress::new(world);
+        progress.loc = legacy.loc;
+        progress.inventory = legacy.inventory;
+        progress.active_events = legacy.active_events;
+        for (room_id, (saved, room)) in legacy.rooms.into_iter().zip(&world.rooms).enumerate() {
+            let room_id = RoomId(room_id);
+            if &saved.entities != room.get_entities() {
+                progress.room_entities.insert(room_id, saved.entities);
+            }
+            if &saved.actors != room.get_actors() {
+                progress.room_actors.insert(room_id, saved.actors);
+            }
+            let added_exits: HashMap<Direction, RoomId> = saved
+                .exits
+                .into_iter()
+                .filter(|(dir, target)| room.get_exit(dir.clone()) != Some(*target))
+                .collect();
+            if !added_exits.is_empty() {
+                progress.added_exits.insert(room_id, added_exits);
+            }
+        }
+        progress
+    } else {
+        serde_json::from_value(save)?
+    };
+    let save = SaveGame {
+        format_version: 1,
+        world_id: world.id.clone(),
+        timestamp: 0,
+        progress,
+    };
+    Ok(serde_json::to_value(save)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn migrates_whole_state_dumps() {
+        let legacy = r#"{"loc":2,"inventory":[4],"rooms":[
+            {"name":"Entrance","description":"","entities":[3],"actors":[],"exits":{"North":1}},
+            {"name":"Corridor","description":"","entities":[],"actors":[],"exits":{"South":0,"East":2}},
+            {"name":"Chamber","description":"","entities":[],"actors":[],"exits":{"West":1,"North":3}},
+            {"name":"Treasure Room","description":"","entities":[1],"actors":[],"exits":{"South":2}}],
+            "active_events":[1,3],"log":""}"#;
+        let world = World::default();
+        let save = SaveGame::from_json(legacy, &world).unwrap();
+        assert_eq!(save.format_version, SAVE_FORMAT_VERSION);
+        assert_eq!(save.world_id, world.id);
+        assert_eq!(save.progress.loc, RoomId(2));
+        assert_eq!(save.progress.room_actors[&RoomId(2)], HashSet::new());
+        assert_eq!(
+            save.progress.added_exits[&RoomId(2)][&Direction::North],
+            RoomId(3)
+        );
+        assert!(save.progress.room_entities.is_empty());
+    }
+
+    #[test]
+    fn rejects_saves_with_unknown_ids() {
+        let world = World::default();
+        let mut progress = Progress::new(&world);
+        progress.active_events.insert(EventId(17));
+        let save = SaveGame::new(&world, progress, 0).to_json().unwrap();
+        assert!(matches!(
+            SaveGame::from_json(&save, &world),
+            Err(SaveError::UnknownId {
+                kind: "event",
+                id: 17
+            })
+        ));
+
+        let save = save.replace(r#""loc":0"#, r#""loc":9"#);
+        assert!(matches!(
+            SaveGame::from_json(&save, &world),
+            Err(SaveError::UnknownId {
+                kind: "room",
+                id: 9
+            })
+        ));
+    }
+
+    #[test]
+    fn rejects_saves_of_other_worlds() {
+        let world = World::default();
+        let mut other = world.clone();
+        other.id = "another-world".to_string();
+        let save = SaveGame::new(&other, Progress::new(&other), 0)
+            .to_json()
+            .unwrap();
+        assert!(matches!(
+            SaveGame::from_json(&save, &world),
+            Err(SaveError::WrongWorld { .. })
+        ));
+    }
+}
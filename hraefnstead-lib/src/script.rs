@@ -0,0 +1,667 @@
+//! A small scripting language for the conditions and effects of events.
+//!
+//! Scripts are compiled when the world is loaded, so syntax errors and
+//! unknown keys are reported right away instead of in the middle of a game.
+//! A script is a list of statements, each ended by `;`:
+//!
+//! ```text
+//! # the bed is searched once, the third attempt finds the coin
+//! add bed_searched 1;
+//! if count bed_searched >= 3 and not flag coin_found {
+//!     say "Something glints between the planks.";
+//!     place copper_coin;
+//!     set coin_found;
+//! } else {
+//!     say "Nothing but dust.";
+//! }
+//! ```
+//!
+//! Statements:
+//!
+//! * `say "text";` shows a message
+//! * `give entity;` and `remove entity;` put an entity into or take it out of
+//!   the inventory
+//! * `place entity;` puts an entity into the current room
+//! * `move room;` moves the player
+//! * `set flag;` and `clear flag;` change a flag
+//! * `add counter 1;` adds a (possibly negative) number to a counter, counters
+//!   start at 0
+//! * `describe room "text";` replaces the description of a room
+//! * `exit north to room;` opens an exit from the current room, a named exit
+//!   is given in quotes, e.g. `exit "hatch" to cellar;`
+//! * `activate event;` and `deactivate event;`
+//! * `win;` and `die;` end the game
+//! * `if condition { ... } else { ... }`, the `else` part is optional and may
+//!   be another `if`
+//!
+//! Conditions are `flag name`, `has entity` (in the inventory), `here entity`
+//! (in the current room), `at room`, `count counter >= 3` (with `==`, `!=`,
+//! `<`, `<=`, `>` or `>=`), `true` and `false`, combined with `not`, `and`,
+//! `or` and parentheses. Everything after `#` on a line is a comment.
+//!
+//! Scripts only act through the game state, have no loops and therefore
+//! always terminate.
+
+use crate::direction::Direction;
+use crate::id::{EntityId, EventId, RoomId};
+use crate::state::State;
+use crate::victory::Victory;
+
+use std::fmt::Display;
+
+use serde::{Deserialize, Serialize};
+
+/// A compiled script.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, Eq, PartialEq)]
+pub struct Script(pub Vec<Statement>);
+
+#[derive(Debug, Clone, Serialize, Deserialize, Eq, PartialEq)]
+pub enum Statement {
+    Say(String),
+    Give(EntityId),
+    Remove(EntityId),
+    Place(EntityId),
+    Move(RoomId),
+    Set(String),
+    Clear(String),
+    Add(String, i64),
+    Describe(RoomId, String),
+    Exit(Direction, RoomId),
+    Activate(EventId),
+    Deactivate(EventId),
+    Win,
+    Die,
+    If(Expr, Script, Script),
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Eq, PartialEq)]
+pub enum Expr {
+    Bool(bool),
+    Flag(String),
+    Has(EntityId),
+    Here(EntityId),
+    At(RoomId),
+    Count(String, Comparison, i64),
+    Not(Box<Expr>),
+    And(Box<Expr>, Box<Expr>),
+    Or(Box<Expr>, Box<Expr>),
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, Eq, PartialEq)]
+pub enum Comparison {
+    Equal,
+    NotEqual,
+    Less,
+    LessOrEqual,
+    Greater,
+    GreaterOrEqual,
+}
+
+/// A syntax error in a script.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ScriptError {
+    pub line: usize,
+    pub column: usize,
+    pub message: String,
+}
+
+impl Display for ScriptError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "line {}, column {}: {}",
+            self.line, self.column, self.message
+        )
+    }
+}
+
+/// Resolves the keys used in a script to ids while compiling it.
+pub(crate) trait Keys {
+    fn room(&mut self, key: &str) -> RoomId;
+    fn entity(&mut self, key: &str) -> EntityId;
+    fn event(&mut self, key: &str) -> EventId;
+}
+
+impl Script {
+    pub(crate) fn compile(source: &str, keys: &mut dyn Keys) -> Result<Script, ScriptError> {
+        let mut parser = Parser::new(source, keys)?;
+        let script = parser.statements()?;
+        match parser.peek() {
+            None => Ok(script),
+            Some(_) => Err(parser.error("expected a statement")),
+        }
+    }
+
+    /// The exits the script may open.
+    pub fn exits(&self) -> Vec<&Direction> {
+        self.0
+            .iter()
+            .flat_map(|statement| match statement {
+                Statement::Exit(direction, _) => vec![direction],
+                Statement::If(_, then, otherwise) => {
+                    then.exits().into_iter().chain(otherwise.exits()).collect()
+                }
+                _ => Vec::new(),
+            })
+            .collect()
+    }
+
+    /// Run the script, stopping early when it ends the game.
+    pub fn run(&self, state: &mut State) -> Victory {
+        for statement in self.0.iter() {
+            let victory = statement.run(state);
+            if victory != Victory::None {
+                return victory;
+            }
+        }
+        Victory::None
+    }
+}
+
+impl Statement {
+    fn run(&self, state: &mut State) -> Victory {
+        match self {
+            Statement::Say(text) => state.log(&format!("\n{text}")),
+            Statement::Give(entity) => state.add_to_inventory(*entity),
+            Statement::Remove(entity) => state.remove_from_inventory(*entity),
+            Statement::Place(entity) => state.add_entity_to_room(*entity),
+            Statement::Move(room) => state.set_location(*room),
+            Statement::Set(flag) => state.set_flag(flag),
+            Statement::Clear(flag) => state.clear_flag(flag),
+            Statement::Add(counter, amount) => state.add_to_counter(counter, *amount),
+            Statement::Describe(room, text) => state.set_room_description(*room, text),
+            Statement::Exit(direction, room) => state.add_exit(direction.clone(), *room),
+            Statement::Activate(event) => state.activate_event(event),
+            Statement::Deactivate(event) => state.de_activate_event(event),
+            Statement::Win => return Victory::Won,
+            Statement::Die => return Victory::GameOver,
+            Statement::If(condition, then, otherwise) => {
+                return if condition.eval(state) {
+                    then.run(state)
+                } else {
+                    otherwise.run(state)
+                };
+            }
+        }
+        Victory::None
+    }
+}
+
+impl Expr {
+    pub fn eval(&self, state: &State) -> bool {
+        match self {
+            Expr::Bool(value) => *value,
+            Expr::Flag(flag) => state.has_flag(flag),
+            Expr::Has(entity) => state.get_inventory().contains(entity),
+            Expr::Here(entity) => state.get_room_entities().contains(entity),
+            Expr::At(room) => state.get_progress().loc == *room,
+            Expr::Count(counter, comparison, value) => {
+                let count = state.get_counter(counter);
+                match comparison {
+                    Comparison::Equal => count == *value,
+                    Comparison::NotEqual => count != *value,
+                    Comparison::Less => count < *value,
+                    Comparison::LessOrEqual => count <= *value,
+                    Comparison::Greater => count > *value,
+                    Comparison::GreaterOrEqual => count >= *value,
+                }
+            }
+            Expr::Not(expr) => !expr.eval(state),
+            Expr::And(left, right) => left.eval(state) && right.eval(state),
+            Expr::Or(left, right) => left.eval(state) || right.eval(state),
+        }
+    }
+
+    pub(crate) fn compile(source: &str, keys: &mut dyn Keys) -> Result<Expr, ScriptError> {
+        let mut parser = Parser::new(source, keys)?;
+        let expr = parser.expr()?;
+        match parser.peek() {
+            None => Ok(expr),
+            Some(_) => Err(parser.error("unexpected text after the condition")),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum Token {
+    Word(String),
+    Text(String),
+    Number(i64),
+    Symbol(&'static str),
+}
+
+impl Display for Token {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Token::Word(word) => write!(f, "'{word}'"),
+            Token::Text(_) => write!(f, "a text"),
+            Token::Number(number) => write!(f, "'{number}'"),
+            Token::Symbol(symbol) => write!(f, "'{symbol}'"),
+        }
+    }
+}
+
+const SYMBOLS: [&str; 13] = [
+    ">=", "<=", "==", "!=", ">", "<", ";", ":", ",", "{", "}", "(", ")",
+];
+
+/// Split a script into tokens with their line and column, also used for
+/// the lines of the world language in [`crate::dsl`].
+pub(crate) fn tokenize(source: &str) -> Result<Vec<(Token, usize, usize)>, ScriptError> {
+    let mut tokens = Vec::new();
+    for (line, text) in source.lines().enumerate() {
+        let line = line + 1;
+        let chars: Vec<char> = text.chars().collect();
+        let mut i = 0;
+        while i < chars.len() {
+            let column = i + 1;
+            let c = chars[i];
+            if c == '#' {
+                break;
+            } else if c.is_whitespace() {
+                i += 1;
+            } else if c == '"' {
+                let mut string = String::new();
+                i += 1;
+                loop {
+                    match chars.get(i) {
+                        None => {
+                            return Err(ScriptError {
+                                line,
+                                column,
+                                message: "text is not closed by '\"'".to_string(),
+                            })
+                        }
+                        Some('"') => break,
+                        Some('\\') if chars.get(i + 1) == Some(&'n') => {
+                            string.push('\n');
+                            i += 1;
+                        }
+                        Some('\\') if chars.get(i + 1).is_some() => {
+                            string.push(chars[i + 1]);
+                            i += 1;
+                        }
+                        Some(c) => string.push(*c),
+                    }
+                    i += 1;
+                }
+                i += 1;
+                tokens.push((Token::Text(string), line, column));
+            } else if c.is_ascii_digit()
+                || (c == '-' && chars.get(i + 1).is_some_and(char::is_ascii_digit))
+            {
+                let start = i;
+                i += 1;
+                while chars.get(i).is_some_and(char::is_ascii_digit) {
+                    i += 1;
+                }
+                let number: String = chars[start..i].iter().collect();
+                let number = number.parse().map_err(|_| ScriptError {
+                    line,
+                    column,
+                    message: format!("number {number} is too large"),
+                })?;
+                tokens.push((Token::Number(number), line, column));
+            } else if c.is_alphanumeric() || c == '_' {
+                let start = i;
+                while chars
+                    .get(i)
+                    .is_some_and(|c| c.is_alphanumeric() || *c == '_' || *c == '-')
+                {
+                    i += 1;
+                }
+                tokens.push((Token::Word(chars[start..i].iter().collect()), line, column));
+            } else if let Some(symbol) = SYMBOLS.iter().find(|symbol| {
+                symbol
+                    .chars()
+                    .enumerate()
+                    .all(|(n, s)| chars.get(i + n) == Some(&s))
+            }) {
+                i += symbol.len();
+                tokens.push((Token::Symbol(symbol), line, column));
+            } else {
+                return Err(ScriptError {
+                    line,
+                    column,
+                    message: format!("unexpected character '{c}'"),
+                });
+            }
+        }
+    }
+    Ok(tokens)
+}
+
+struct Parser<'k> {
+    tokens: Vec<(Token, usize, usize)>,
+    position: usize,
+    keys: &'k mut dyn Keys,
+}
+
+impl<'k> Parser<'k> {
+    fn new(source: &str, keys: &'k mut dyn Keys) -> Result<Self, ScriptError> {
+        Ok(Self {
+            tokens: tokenize(source)?,
+            position: 0,
+            keys,
+        })
+    }
+
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.position).map(|(token, _, _)| token)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.peek().cloned();
+        self.position += 1;
+        token
+    }
+
+    /// An error at the current token, or at the end of the script.
+    fn error(&self, message: &str) -> ScriptError {
+        let (line, column) = match self.tokens.get(self.position) {
+            Some((_, line, column)) => (*line, *column),
+            None => match self.tokens.last() {
+                Some((token, line, column)) => (*line, column + token.to_string().len()),
+                None => (1, 1),
+            },
+        };
+        let found = match self.peek() {
+            Some(token) => format!("found {token}"),
+            None => "found the end of the script".to_string(),
+        };
+        ScriptError {
+            line,
+            column,
+            message: format!("{message}, {found}"),
+        }
+    }
+
+    fn accept(&mut self, symbol: &str) -> bool {
+        let found = matches!(self.peek(), Some(Token::Symbol(s)) if *s == symbol)
+            || matches!(self.peek(), Some(Token::Word(w)) if w == symbol);
+        if found {
+            self.position += 1;
+        }
+        found
+    }
+
+    fn expect(&mut self, symbol: &str) -> Result<(), ScriptError> {
+        if self.accept(symbol) {
+            Ok(())
+        } else {
+            Err(self.error(&format!("expected '{symbol}'")))
+        }
+    }
+
+    fn word(&mut self, what: &str) -> Result<String, ScriptError> {
+        match self.peek() {
+            Some(Token::Word(word)) => {
+                let word = word.clone();
+                self.position += 1;
+                Ok(word)
+            }
+            _ => Err(self.error(&format!("expected {what}"))),
+        }
+    }
+
+    fn text(&mut self) -> Result<String, ScriptError> {
+        match self.peek() {
+            Some(Token::Text(text)) => {
+                let text = text.clone();
+                self.position += 1;
+                Ok(text)
+            }
+            _ => Err(self.error("expected a text in quotes")),
+        }
+    }
+
+    fn number(&mut self) -> Result<i64, ScriptError> {
+        match self.peek() {
+            Some(Token::Number(number)) => {
+                let number = *number;
+                self.position += 1;
+                Ok(number)
+            }
+            _ => Err(self.error("expected a number")),
+        }
+    }
+
+    fn room(&mut self) -> Result<RoomId, ScriptError> {
+        let key = self.word("the key of a room")?;
+        Ok(self.keys.room(&key))
+    }
+
+    fn entity(&mut self) -> Result<EntityId, ScriptError> {
+        let key = self.word("the key of an entity")?;
+        Ok(self.keys.entity(&key))
+    }
+
+    fn event(&mut self) -> Result<EventId, ScriptError> {
+        let key = self.word("the key of an event")?;
+        Ok(self.keys.event(&key))
+    }
+
+    /// Statements up to the end of the script or the end of a block.
+    fn statements(&mut self) -> Result<Script, ScriptError> {
+        let mut statements = Vec::new();
+        while !matches!(self.peek(), None | Some(Token::Symbol("}"))) {
+            statements.push(self.statement()?);
+        }
+        Ok(Script(statements))
+    }
+
+    fn block(&mut self) -> Result<Script, ScriptError> {
+        self.expect("{")?;
+        let script = self.statements()?;
+        self.expect("}")?;
+        Ok(script)
+    }
+
+    fn statement(&mut self) -> Result<Statement, ScriptError> {
+        let keyword = self.word("a statement")?;
+        let statement = match keyword.as_str() {
+            "say" => Statement::Say(self.text()?),
+            "give" => Statement::Give(self.entity()?),
+            "remove" => Statement::Remove(self.entity()?),
+            "place" => Statement::Place(self.entity()?),
+            "move" => Statement::Move(self.room()?),
+            "set" => Statement::Set(self.word("the name of a flag")?),
+            "clear" => Statement::Clear(self.word("the name of a flag")?),
+            "add" => Statement::Add(self.word("the name of a counter")?, self.number()?),
+            "describe" => Statement::Describe(self.room()?, self.text()?),
+            "exit" => {
+                let direction = match self.peek() {
+                    Some(Token::Text(_)) => Direction::Named(self.text()?.to_lowercase()),
+                    _ => {
+                        let direction = self.word("a direction")?;
+                        let Some(direction) = Direction::from_str(&direction) else {
+                            self.position -= 1;
+                            return Err(self.error("expected a direction"));
+                        };
+                        direction
+                    }
+                };
+                self.expect("to")?;
+                Statement::Exit(direction, self.room()?)
+            }
+            "activate" => Statement::Activate(self.event()?),
+            "deactivate" => Statement::Deactivate(self.event()?),
+            "win" => Statement::Win,
+            "die" => Statement::Die,
+            "if" => return self.if_statement(),
+            _ => {
+                self.position -= 1;
+                return Err(self.error("expected a statement"));
+            }
+        };
+        self.expect(";")?;
+        Ok(statement)
+    }
+
+    fn if_statement(&mut self) -> Result<Statement, ScriptError> {
+        let condition = self.expr()?;
+        let then = self.block()?;
+        let otherwise = if !self.accept("else") {
+            Script::default()
+        } else if self.accept("if") {
+            Script(vec![self.if_statement()?])
+        } else {
+            self.block()?
+        };
+        Ok(Statement::If(condition, then, otherwise))
+    }
+
+    fn expr(&mut self) -> Result<Expr, ScriptError> {
+        let mut expr = self.and_expr()?;
+        while self.accept("or") {
+            expr = Expr::Or(Box::new(expr), Box::new(self.and_expr()?));
+        }
+        Ok(expr)
+    }
+
+    fn and_expr(&mut self) -> Result<Expr, ScriptError> {
+        let mut expr = self.unary_expr()?;
+        while self.accept("and") {
+            expr = Expr::And(Box::new(expr), Box::new(self.unary_expr()?));
+        }
+        Ok(expr)
+    }
+
+    fn unary_expr(&mut self) -> Result<Expr, ScriptError> {
+        if self.accept("not") {
+            return Ok(Expr::Not(Box::new(self.unary_expr()?)));
+        }
+        if self.accept("(") {
+            let expr = self.expr()?;
+            self.expect(")")?;
+            return Ok(expr);
+        }
+        let keyword = self.word("a condition")?;
+        Ok(match keyword.as_str() {
+            "true" => Expr::Bool(true),
+            "false" => Expr::Bool(false),
+            "flag" => Expr::Flag(self.word("the name of a flag")?),
+            "has" => Expr::Has(self.entity()?),
+            "here" => Expr::Here(self.entity()?),
+            "at" => Expr::At(self.room()?),
+            "count" => {
+                let counter = self.word("the name of a counter")?;
+                let comparison = match self.next() {
+                    Some(Token::Symbol("==")) => Comparison::Equal,
+                    Some(Token::Symbol("!=")) => Comparison::NotEqual,
+                    Some(Token::Symbol("<")) => Comparison::Less,
+                    Some(Token::Symbol("<=")) => Comparison::LessOrEqual,
+                    Some(Token::Symbol(">")) => Comparison::Greater,
+                    Some(Token::Symbol(">=")) => Comparison::GreaterOrEqual,
+                    _ => {
+                        self.position -= 1;
+                        return Err(self.error("expected a comparison"));
+                    }
+                };
+                Expr::Count(counter, comparison, self.number()?)
+            }
+            _ => {
+                self.position -= 1;
+                return Err(self.error("expected a condition"));
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Keys are numbered in the order they are first used.
+    #[derive(Default)]
+    struct TestKeys(Vec<String>);
+
+    impl TestKeys {
+        fn id(&mut self, key: &str) -> usize {
+            match self.0.iter().position(|k| k == key) {
+                Some(id) => id,
+                None => {
+                    self.0.push(key.to_string());
+                    self.0.len() - 1
+                }
+            }
+        }
+    }
+
+    impl Keys for TestKeys {
+        fn room(&mut self, key: &str) -> RoomId {
+            RoomId(self.id(key))
+        }
+        fn entity(&mut self, key: &str) -> EntityId {
+            EntityId(self.id(key))
+        }
+        fn event(&mut self, key: &str) -> EventId {
+            EventId(self.id(key))
+        }
+    }
+
+    #[test]
+    fn compiles_branches_and_counters() {
+        let source = r#"
+            add tries 1; # count the attempt
+            if count tries >= 3 and not (flag found or has coin) {
+                say "Found it!";
+                give coin;
+            } else if at cellar { die; } else { say "Nothing."; }
+        "#;
+        let script = Script::compile(source, &mut TestKeys::default()).unwrap();
+        assert_eq!(script.0.len(), 2);
+        let Statement::If(condition, then, otherwise) = &script.0[1] else {
+            panic!("expected an if statement, got {:?}", script.0[1]);
+        };
+        assert_eq!(
+            *condition,
+            Expr::And(
+                Box::new(Expr::Count(
+                    "tries".to_string(),
+                    Comparison::GreaterOrEqual,
+                    3
+                )),
+                Box::new(Expr::Not(Box::new(Expr::Or(
+                    Box::new(Expr::Flag("found".to_string())),
+                    Box::new(Expr::Has(EntityId(0)))
+                ))))
+            )
+        );
+        assert_eq!(then.0[1], Statement::Give(EntityId(0)));
+        assert!(matches!(
+            otherwise.0[0],
+            Statement::If(Expr::At(RoomId(1)), _, _)
+        ));
+    }
+
+    #[test]
+    fn event_scripts_count_and_describe() {
+        let world = crate::dsl::compile(
+            "Start in Entrance\n\
+             Room \"Entrance\": \"You are in the entrance.\"\n    Thing \"A vending machine\" (machine): \"A machine.\"\n\
+             Event \"kick machine\" when attack machine if \"here machine\": say \"You kick the machine.\"; \
+             script \"add kicks 1; if count kicks >= 3 { describe entrance \\\"The display is cracked.\\\"; \
+             deactivate kick_machine; } else { say \\\"It rattles.\\\"; }\"",
+        )
+        .unwrap()
+        .resolve()
+        .unwrap();
+        let mut state = crate::state::State::from_world(world);
+        state.play("attack machine");
+        assert_eq!(state.get_log(), "\nYou kick the machine.\n\n\nIt rattles.");
+        state.play("attack machine, attack machine, attack machine, look");
+        assert_eq!(state.get_counter("kicks"), 3);
+        assert!(state.get_log().contains("The display is cracked."));
+    }
+
+    #[test]
+    fn reports_position_of_syntax_errors() {
+        let error = Script::compile("say \"hi\";\nsay \"there\"\ngive", &mut TestKeys::default())
+            .unwrap_err();
+        assert_eq!((error.line, error.column), (3, 1));
+        assert_eq!(error.message, "expected ';', found 'give'");
+        let error = Expr::compile("count tries => 2", &mut TestKeys::default()).unwrap_err();
+        assert_eq!((error.line, error.column), (1, 13));
+    }
+}
@@ -0,0 +1,839 @@
+//! The authoring format of worlds.
+//!
+//! In a world file every room, entity, actor, condition and event has a
+//! string `key`, and all references between them use these keys. Loading a
+//! world resolves the keys to the ids used by the engine, see
+//! [`WorldSource::resolve`].
+
+use crate::actor::Actor;
+use crate::command::Command;
+use crate::condition::Condition;
+use crate::direction::Direction;
+use crate::door::{Door, DoorState};
+use crate::entity::Entity;
+use crate::event::Event;
+use crate::id::{ActorId, DoorId, EntityId, EventId, RoomId};
+use crate::locale::Locale;
+use crate::room::Room;
+use crate::script::{Expr, Keys, Script, ScriptError};
+use crate::verb::Vocabulary;
+use crate::world::{World, WorldError};
+
+use std::collections::{BTreeMap, HashMap};
+use std::fmt::Display;
+
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub struct WorldSource {
+    #[serde(default)]
+    pub id: String,
+    #[serde(default)]
+    pub version: u32,
+    pub start: String,
+    pub rooms: Vec<RoomSource>,
+    #[serde(default)]
+    pub entities: Vec<ThingSource>,
+    #[serde(default)]
+    pub actors: Vec<ThingSource>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub doors: Vec<DoorSource>,
+    #[serde(default)]
+    pub craft_recipes: BTreeMap<String, String>,
+    #[serde(default)]
+    pub conditions: Vec<ConditionSource>,
+    #[serde(default)]
+    pub events: Vec<EventSource>,
+    #[serde(default)]
+    pub active_events: Vec<String>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub synonyms: BTreeMap<String, Vec<String>>,
+    #[serde(default, skip_serializing_if = "is_default")]
+    pub locale: Locale,
+}
+
+fn is_default(locale: &Locale) -> bool {
+    *locale == Locale::default()
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub struct RoomSource {
+    pub key: String,
+    pub name: String,
+    pub description: String,
+    #[serde(default)]
+    pub entities: Vec<String>,
+    #[serde(default)]
+    pub actors: Vec<String>,
+    #[serde(default)]
+    pub exits: BTreeMap<Direction, String>,
+}
+
+/// An entity or an actor.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub struct ThingSource {
+    pub key: String,
+    pub name: String,
+    pub description: String,
+    #[serde(default)]
+    pub aliases: Vec<String>,
+}
+
+/// A door between two rooms.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub struct DoorSource {
+    pub key: String,
+    pub name: String,
+    #[serde(default)]
+    pub aliases: Vec<String>,
+    /// The exit the door stands in, by the key of each of its two rooms.
+    pub sides: BTreeMap<String, Direction>,
+    /// The key of the entity that locks and unlocks the door.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub lock: Option<String>,
+    #[serde(default)]
+    pub state: DoorState,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub struct ConditionSource {
+    pub key: String,
+    pub condition: ConditionExpr,
+}
+
+/// A [`Condition`] referring to rooms, entities, actors and other conditions by key.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub enum ConditionExpr {
+    CommandIs(Command),
+    Location(String),
+    ObjectInInventory(String),
+    Actor(String),
+    ActorHere(String),
+    All(Vec<ConditionExpr>),
+    Any(Vec<ConditionExpr>),
+    Not(Box<ConditionExpr>),
+    /// A condition in the event script language, see [`crate::script`].
+    Script(String),
+    // The forms of the index-linked conditions of older world files.
+    And(Box<ConditionExpr>, Box<ConditionExpr>),
+    Or(Box<ConditionExpr>, Box<ConditionExpr>),
+    NotAnd(Box<ConditionExpr>, Box<ConditionExpr>),
+    NotOr(Box<ConditionExpr>, Box<ConditionExpr>),
+    NotCommandIs(Command),
+    NotLocation(String),
+    NotObjectInInventory(String),
+    /// The key of a condition in the `conditions` list.
+    #[serde(untagged)]
+    Named(String),
+    /// The position of a condition in the `conditions` list, as older world
+    /// files link their conditions.
+    #[serde(untagged)]
+    Index(usize),
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub struct EventSource {
+    pub key: String,
+    pub condition: ConditionExpr,
+    pub message: String,
+    #[serde(default)]
+    pub command_stack: Vec<CommandSource>,
+    /// Statements in the event script language, run after the command stack.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub script: Option<String>,
+}
+
+/// A command of an event, the commands changing the world refer to it by key.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub enum CommandSource {
+    AddItemToRoom(String),
+    DeActivateEvent(String),
+    ActivateEvent(String),
+    RemoveActor(String),
+    Consume(String),
+    AddExit(Direction, String),
+    #[serde(untagged)]
+    Other(Command),
+}
+
+/// A reference in a world source that could not be resolved.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum KeyError {
+    Unknown {
+        context: String,
+        kind: &'static str,
+        key: String,
+    },
+    Duplicate {
+        kind: &'static str,
+        key: String,
+    },
+    /// A command in an event uses a numeric id instead of a key.
+    NumericId {
+        context: String,
+    },
+    /// The named conditions refer to each other in a cycle.
+    ConditionCycle(Vec<String>),
+    /// A door that does not connect exactly two rooms.
+    DoorSides {
+        door: String,
+    },
+    Script {
+        context: String,
+        error: ScriptError,
+    },
+}
+
+impl Display for KeyError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            KeyError::Unknown { context, kind, key } => {
+                write!(f, "{context}: unknown {kind} '{key}'")
+            }
+            KeyError::Duplicate { kind, key } => write!(f, "{kind} '{key}' is defined twice"),
+            KeyError::NumericId { context } => {
+                write!(f, "{context}: refer to other objects by key, not by number")
+            }
+            KeyError::ConditionCycle(cycle) => write!(
+                f,
+                "conditions refer to each other in a cycle: {}",
+                cycle.join(" -> ")
+            ),
+            KeyError::DoorSides { door } => {
+                write!(f, "door '{door}' must connect exactly two rooms")
+            }
+            KeyError::Script { context, error } => write!(f, "{context}: script error at {error}"),
+        }
+    }
+}
+
+impl WorldSource {
+    /// Replace all keys by ids and named conditions by their definition.
+    /// Rooms and events are numbered from 0, entities and actors from 1, in
+    /// the order they are declared.
+    pub fn resolve(&self) -> Result<World, WorldError> {
+        let mut resolver = Resolver::default();
+        for condition in self.conditions.iter() {
+            resolver.condition_keys.push(&condition.key);
+            let previous = resolver
+                .named_conditions
+                .insert(&condition.key, &condition.condition);
+            if previous.is_some() {
+                resolver.errors.push(KeyError::Duplicate {
+                    kind: "condition",
+                    key: condition.key.clone(),
+                });
+            }
+        }
+        resolver.rooms = resolver.index("room", self.rooms.iter().map(|r| &r.key), 0, RoomId);
+        resolver.entities =
+            resolver.index("entity", self.entities.iter().map(|e| &e.key), 1, EntityId);
+        resolver.actors = resolver.index("actor", self.actors.iter().map(|a| &a.key), 1, ActorId);
+        resolver.events = resolver.index("event", self.events.iter().map(|e| &e.key), 0, EventId);
+        resolver.index("door", self.doors.iter().map(|d| &d.key), 0, DoorId);
+
+        let start = resolver.room("start", &self.start);
+        let mut rooms: Vec<Room> = self
+            .rooms
+            .iter()
+            .map(|room| {
+                let context = format!("room '{}'", room.key);
+                Room::new(
+                    &room.name,
+                    &room.description,
+                    room.entities
+                        .iter()
+                        .map(|e| resolver.entity(&context, e))
+                        .collect(),
+                    room.actors
+                        .iter()
+                        .map(|a| resolver.actor(&context, a))
+                        .collect(),
+                    room.exits
+                        .iter()
+                        .map(|(dir, r)| (dir.clone(), resolver.room(&context, r)))
+                        .collect(),
+                )
+            })
+            .collect();
+        let doors = self
+            .doors
+            .iter()
+            .filter_map(|door| resolver.door(door, &mut rooms))
+            .collect();
+        let entities = self
+            .entities
+            .iter()
+            .enumerate()
+            .map(|(index, e)| {
+                let aliases = e.aliases.iter().cloned().collect();
+                (
+                    EntityId(index + 1),
+                    Entity::new(&e.name, &e.description, aliases),
+                )
+            })
+            .collect();
+        let actors = self
+            .actors
+            .iter()
+            .enumerate()
+            .map(|(index, a)| {
+                let aliases = a.aliases.iter().cloned().collect();
+                (
+                    ActorId(index + 1),
+                    Actor::new(&a.name, &a.description, aliases),
+                )
+            })
+            .collect();
+        let craft_recipes = self
+            .craft_recipes
+            .iter()
+            .map(|(ingredient, product)| {
+                (
+                    resolver.entity("craft recipes", ingredient),
+                    resolver.entity("craft recipes", product),
+                )
+            })
+            .collect();
+        for condition in self.conditions.iter() {
+            resolver.named_condition("conditions", &condition.key);
+        }
+        let events = self
+            .events
+            .iter()
+            .enumerate()
+            .map(|(event_id, event)| {
+                let context = format!("event '{}'", event.key);
+                let mut command_stack: Vec<Command> = event
+                    .command_stack
+                    .iter()
+                    .map(|command| resolver.command(&context, command))
+                    .collect();
+                let script = match &event.script {
+                    Some(script) => {
+                        command_stack.push(Command::RunScript(EventId(event_id)));
+                        resolver.script(&context, script)
+                    }
+                    None => Script::default(),
+                };
+                Event::new(
+                    resolver.condition_expr(&context, &event.condition),
+                    event.message.clone(),
+                    command_stack,
+                    script,
+                )
+            })
+            .collect();
+        let active_events = self
+            .active_events
+            .iter()
+            .map(|e| resolver.event("active events", e))
+            .collect();
+        let mut vocabulary = Vocabulary::new(self.locale);
+        for (verb, synonyms) in self.synonyms.iter() {
+            for synonym in synonyms {
+                if vocabulary.find(&synonym.to_lowercase()).is_some() {
+                    resolver.errors.push(KeyError::Duplicate {
+                        kind: "verb",
+                        key: synonym.clone(),
+                    });
+                } else if !vocabulary.add_synonym(verb, synonym) {
+                    resolver.errors.push(KeyError::Unknown {
+                        context: "synonyms".to_string(),
+                        kind: "verb",
+                        key: verb.clone(),
+                    });
+                }
+            }
+        }
+
+        if !resolver.errors.is_empty() {
+            return Err(WorldError::Keys(resolver.errors));
+        }
+        Ok(World {
+            id: self.id.clone(),
+            version: self.version,
+            start,
+            rooms,
+            entities,
+            actors,
+            doors,
+            craft_recipes,
+            events,
+            active_events,
+            synonyms: self.synonyms.clone(),
+            locale: self.locale,
+        })
+    }
+}
+
+#[derive(Default)]
+struct Resolver<'a> {
+    rooms: HashMap<String, RoomId>,
+    entities: HashMap<String, EntityId>,
+    actors: HashMap<String, ActorId>,
+    events: HashMap<String, EventId>,
+    named_conditions: HashMap<&'a str, &'a ConditionExpr>,
+    /// The keys of the named conditions in the order they are declared.
+    condition_keys: Vec<&'a str>,
+    resolved_conditions: HashMap<String, Condition>,
+    /// The named conditions currently being resolved, to detect cycles.
+    resolving: Vec<String>,
+    errors: Vec<KeyError>,
+}
+
+impl<'a> Resolver<'a> {
+    fn index<'k, T>(
+        &mut self,
+        kind: &'static str,
+        keys: impl Iterator<Item = &'k String>,
+        first: usize,
+        id: fn(usize) -> T,
+    ) -> HashMap<String, T> {
+        let mut ids = HashMap::new();
+        for (index, key) in keys.enumerate() {
+            if ids.insert(key.clone(), id(first + index)).is_some() {
+                self.errors.push(KeyError::Duplicate {
+                    kind,
+                    key: key.clone(),
+                });
+            }
+        }
+        ids
+    }
+
+    /// Look up a key; unknown keys are recorded and resolve to a dummy id so
+    /// that all errors can be reported at once.
+    fn lookup<T: Copy>(
+        ids: &HashMap<String, T>,
+        errors: &mut Vec<KeyError>,
+        kind: &'static str,
+        context: &str,
+        key: &str,
+        dummy: T,
+    ) -> T {
+        ids.get(key).copied().unwrap_or_else(|| {
+            errors.push(KeyError::Unknown {
+                context: context.to_string(),
+                kind,
+                key: key.to_string(),
+            });
+            dummy
+        })
+    }
+
+    fn room(&mut self, context: &str, key: &str) -> RoomId {
+        Self::lookup(
+            &self.rooms,
+            &mut self.errors,
+            "room",
+            context,
+            key,
+            RoomId(0),
+        )
+    }
+
+    fn entity(&mut self, context: &str, key: &str) -> EntityId {
+        Self::lookup(
+            &self.entities,
+            &mut self.errors,
+            "entity",
+            context,
+            key,
+            EntityId(0),
+        )
+    }
+
+    fn actor(&mut self, context: &str, key: &str) -> ActorId {
+        Self::lookup(
+            &self.actors,
+            &mut self.errors,
+            "actor",
+            context,
+            key,
+            ActorId(0),
+        )
+    }
+
+    fn event(&mut self, context: &str, key: &str) -> EventId {
+        Self::lookup(
+            &self.events,
+            &mut self.errors,
+            "event",
+            context,
+            key,
+            EventId(0),
+        )
+    }
+
+    /// Resolve a door and add its exits to the rooms it connects.
+    fn door(&mut self, door: &DoorSource, rooms: &mut [Room]) -> Option<Door> {
+        let context = format!("door '{}'", door.key);
+        let sides: Vec<(RoomId, Direction)> = door
+            .sides
+            .iter()
+            .map(|(room, dir)| (self.room(&context, room), dir.clone()))
+            .collect();
+        for ((key, dir), (room, _)) in door.sides.iter().zip(sides.iter()) {
+            if rooms
+                .get(room.0)
+                .is_some_and(|room| room.exits.contains_key(dir))
+            {
+                self.errors.push(KeyError::Duplicate {
+                    kind: "exit",
+                    key: format!("{key} {dir}"),
+                });
+            }
+        }
+        let Ok(sides) = <[(RoomId, Direction); 2]>::try_from(sides) else {
+            self.errors.push(KeyError::DoorSides {
+                door: door.key.clone(),
+            });
+            return None;
+        };
+        for ((room, dir), (other, _)) in sides.iter().zip(sides.iter().rev()) {
+            if let Some(room) = rooms.get_mut(room.0) {
+                room.add_exit(dir.clone(), *other);
+            }
+        }
+        Some(Door {
+            name: door.name.clone(),
+            aliases: door.aliases.iter().cloned().collect(),
+            key: door.lock.as_ref().map(|key| self.entity(&context, key)),
+            sides,
+            state: door.state,
+        })
+    }
+
+    /// Resolve a named condition, each one is only resolved once.
+    fn named_condition(&mut self, context: &str, key: &str) -> Condition {
+        if let Some(condition) = self.resolved_conditions.get(key) {
+            return condition.clone();
+        }
+        let Some(expr) = self.named_conditions.get(key).copied() else {
+            self.errors.push(KeyError::Unknown {
+                context: context.to_string(),
+                kind: "condition",
+                key: key.to_string(),
+            });
+            return Condition::All(Vec::new());
+        };
+        if let Some(start) = self.resolving.iter().position(|k| k == key) {
+            let mut cycle = self.resolving[start..].to_vec();
+            cycle.push(key.to_string());
+            self.errors.push(KeyError::ConditionCycle(cycle));
+            return Condition::All(Vec::new());
+        }
+        self.resolving.push(key.to_string());
+        let condition = self.condition_expr(&format!("condition '{key}'"), expr);
+        self.resolving.pop();
+        self.resolved_conditions
+            .insert(key.to_string(), condition.clone());
+        condition
+    }
+
+    fn condition_expr(&mut self, context: &str, condition: &ConditionExpr) -> Condition {
+        match condition {
+            ConditionExpr::CommandIs(command) => Condition::CommandIs(command.clone()),
+            ConditionExpr::Location(room) => Condition::Location(self.room(context, room)),
+            ConditionExpr::ObjectInInventory(entity) => {
+                Condition::ObjectInInventory(self.entity(context, entity))
+            }
+            ConditionExpr::Actor(actor) => Condition::Actor(self.actor(context, actor)),
+            ConditionExpr::ActorHere(actor) => Condition::ActorHere(self.actor(context, actor)),
+            ConditionExpr::All(conditions) => Condition::All(
+                conditions
+                    .iter()
+                    .map(|c| self.condition_expr(context, c))
+                    .collect(),
+            ),
+            ConditionExpr::Any(conditions) => Condition::Any(
+                conditions
+                    .iter()
+                    .map(|c| self.condition_expr(context, c))
+                    .collect(),
+            ),
+            ConditionExpr::Not(c) => Condition::Not(Box::new(self.condition_expr(context, c))),
+            ConditionExpr::Named(key) => self.named_condition(context, key),
+            ConditionExpr::Index(index) => match self.condition_keys.get(*index).copied() {
+                Some(key) => self.named_condition(context, key),
+                None => {
+                    self.errors.push(KeyError::Unknown {
+                        context: context.to_string(),
+                        kind: "condition",
+                        key: index.to_string(),
+                    });
+                    Condition::All(Vec::new())
+                }
+            },
+            ConditionExpr::Script(source) => {
+                let compiled = Expr::compile(source, &mut ScriptKeys::new(self, context));
+                match compiled {
+                    Ok(expr) => Condition::Script(expr),
+                    Err(error) => {
+                        self.errors.push(KeyError::Script {
+                            context: context.to_string(),
+                            error,
+                        });
+                        Condition::All(Vec::new())
+                    }
+                }
+            }
+            ConditionExpr::And(c1, c2) => Condition::All(vec![
+                self.condition_expr(context, c1),
+                self.condition_expr(context, c2),
+            ]),
+            ConditionExpr::Or(c1, c2) => Condition::Any(vec![
+                self.condition_expr(context, c1),
+                self.condition_expr(context, c2),
+            ]),
+            ConditionExpr::NotAnd(c1, c2) => Condition::Not(Box::new(Condition::All(vec![
+                self.condition_expr(context, c1),
+                self.condition_expr(context, c2),
+            ]))),
+            ConditionExpr::NotOr(c1, c2) => Condition::Not(Box::new(Condition::Any(vec![
+                self.condition_expr(context, c1),
+                self.condition_expr(context, c2),
+            ]))),
+            ConditionExpr::NotCommandIs(command) => {
+                Condition::Not(Box::new(Condition::CommandIs(command.clone())))
+            }
+            ConditionExpr::NotLocation(room) => {
+                Condition::Not(Box::new(Condition::Location(self.room(context, room))))
+            }
+            ConditionExpr::NotObjectInInventory(entity) => Condition::Not(Box::new(
+                Condition::ObjectInInventory(self.entity(context, entity)),
+            )),
+        }
+    }
+
+    fn script(&mut self, context: &str, source: &str) -> Script {
+        let compiled = Script::compile(source, &mut ScriptKeys::new(self, context));
+        compiled.unwrap_or_else(|error| {
+            self.errors.push(KeyError::Script {
+                context: context.to_string(),
+                error,
+            });
+            Script::default()
+        })
+    }
+
+    fn command(&mut self, context: &str, command: &CommandSource) -> Command {
+        match command {
+            CommandSource::AddItemToRoom(entity) => {
+                Command::AddItemToRoom(self.entity(context, entity))
+            }
+            CommandSource::DeActivateEvent(event) => {
+                Command::DeActivateEvent(self.event(context, event))
+            }
+            CommandSource::ActivateEvent(event) => {
+                Command::ActivateEvent(self.event(context, event))
+            }
+            CommandSource::RemoveActor(actor) => Command::RemoveActor(self.actor(context, actor)),
+            CommandSource::Consume(entity) => Command::Consume(self.entity(context, entity)),
+            CommandSource::AddExit(dir, room) => {
+                Command::AddExit(dir.clone(), self.room(context, room))
+            }
+            CommandSource::Other(command) => {
+                if matches!(
+                    command,
+                    Command::AddItemToRoom(_)
+                        | Command::DeActivateEvent(_)
+                        | Command::ActivateEvent(_)
+                        | Command::RemoveActor(_)
+                        | Command::Consume(_)
+                        | Command::AddExit(_, _)
+                        | Command::RunScript(_)
+                ) {
+                    self.errors.push(KeyError::NumericId {
+                        context: context.to_string(),
+                    });
+                }
+                command.clone()
+            }
+        }
+    }
+}
+
+/// Resolves the keys of a script in the context of the event using it.
+struct ScriptKeys<'r, 'a> {
+    resolver: &'r mut Resolver<'a>,
+    context: &'r str,
+}
+
+impl<'r, 'a> ScriptKeys<'r, 'a> {
+    fn new(resolver: &'r mut Resolver<'a>, context: &'r str) -> Self {
+        Self { resolver, context }
+    }
+}
+
+impl Keys for ScriptKeys<'_, '_> {
+    fn room(&mut self, key: &str) -> RoomId {
+        self.resolver.room(self.context, key)
+    }
+
+    fn entity(&mut self, key: &str) -> EntityId {
+        self.resolver.entity(self.context, key)
+    }
+
+    fn event(&mut self, key: &str) -> EventId {
+        self.resolver.event(self.context, key)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::world::DEFAULT_WORLD;
+
+    #[test]
+    fn reports_unknown_and_duplicate_keys() {
+        let mut source: WorldSource = serde_json::from_str(DEFAULT_WORLD).unwrap();
+        source.start = "hall".to_string();
+        source.events[0].command_stack[0] = CommandSource::AddItemToRoom("gold_coin".to_string());
+        source.rooms[1].key = "entrance".to_string();
+        let Err(WorldError::Keys(errors)) = source.resolve() else {
+            panic!("world with broken keys must not resolve");
+        };
+        assert!(errors.contains(&KeyError::Duplicate {
+            kind: "room",
+            key: "entrance".to_string()
+        }));
+        assert!(errors.contains(&KeyError::Unknown {
+            context: "start".to_string(),
+            kind: "room",
+            key: "hall".to_string()
+        }));
+        assert!(errors.contains(&KeyError::Unknown {
+            context: "event 'find_coin'".to_string(),
+            kind: "entity",
+            key: "gold_coin".to_string()
+        }));
+    }
+
+    #[test]
+    fn accepts_index_linked_conditions() {
+        let world: World = r#"{
+            "start": "hall",
+            "rooms": [{"key": "hall", "name": "Hall", "description": ""}],
+            "entities": [{"key": "coin", "name": "A coin", "description": ""}],
+            "conditions": [
+                {"key": "in_hall", "condition": {"Location": "hall"}},
+                {"key": "look", "condition": {"CommandIs": "Look"}},
+                {"key": "look_in_hall", "condition": {"And": ["in_hall", "look"]}},
+                {"key": "no_coin", "condition": {"NotObjectInInventory": "coin"}}
+            ],
+            "events": [
+                {"key": "e", "condition": {"NotAnd": ["look_in_hall", "no_coin"]}, "message": ""}
+            ]
+        }"#
+        .parse()
+        .unwrap();
+        assert_eq!(
+            world.events[0].condition,
+            Condition::Not(Box::new(Condition::All(vec![
+                Condition::All(vec![
+                    Condition::Location(RoomId(0)),
+                    Condition::CommandIs(Command::Look)
+                ]),
+                Condition::Not(Box::new(Condition::ObjectInInventory(EntityId(1)))),
+            ])))
+        );
+    }
+
+    #[test]
+    fn accepts_conditions_linked_by_index() {
+        let world: World = r#"{
+            "start": "hall",
+            "rooms": [{"key": "hall", "name": "Hall", "description": ""}],
+            "conditions": [
+                {"key": "in_hall", "condition": {"Location": "hall"}},
+                {"key": "look", "condition": {"CommandIs": "Look"}},
+                {"key": "look_in_hall", "condition": {"And": [0, 1]}}
+            ],
+            "events": [
+                {"key": "e", "condition": {"NotOr": [2, 1]}, "message": ""}
+            ]
+        }"#
+        .parse()
+        .unwrap();
+        let look_in_hall = Condition::All(vec![
+            Condition::Location(RoomId(0)),
+            Condition::CommandIs(Command::Look),
+        ]);
+        assert_eq!(
+            world.events[0].condition,
+            Condition::Not(Box::new(Condition::Any(vec![
+                look_in_hall,
+                Condition::CommandIs(Command::Look),
+            ])))
+        );
+
+        let result = r#"{
+            "start": "hall",
+            "rooms": [{"key": "hall", "name": "Hall", "description": ""}],
+            "events": [{"key": "e", "condition": {"And": [0, 1]}, "message": ""}]
+        }"#
+        .parse::<World>();
+        let Err(WorldError::Keys(errors)) = result else {
+            panic!("expected key errors");
+        };
+        assert!(errors.contains(&KeyError::Unknown {
+            context: "event 'e'".to_string(),
+            kind: "condition",
+            key: "0".to_string(),
+        }));
+    }
+
+    #[test]
+    fn reports_cyclic_conditions() {
+        let result = r#"{
+            "start": "hall",
+            "rooms": [{"key": "hall", "name": "Hall", "description": ""}],
+            "conditions": [
+                {"key": "a", "condition": {"Any": ["b", {"Location": "hall"}]}},
+                {"key": "b", "condition": {"Not": "a"}}
+            ]
+        }"#
+        .parse::<World>();
+        let Err(WorldError::Keys(errors)) = result else {
+            panic!("cyclic conditions must not resolve");
+        };
+        assert_eq!(
+            errors,
+            vec![KeyError::ConditionCycle(vec![
+                "a".to_string(),
+                "b".to_string(),
+                "a".to_string()
+            ])]
+        );
+    }
+
+    #[test]
+    fn compiles_event_scripts() {
+        let result = r#"{
+            "start": "hall",
+            "rooms": [{"key": "hall", "name": "Hall", "description": ""}],
+            "events": [
+                {"key": "ring", "condition": {"Script": "not flag rung"},
+                 "message": "", "script": "set rung; move cellar;"},
+                {"key": "broken", "condition": {"CommandIs": "Look"}, "message": "",
+                 "script": "say \"unclosed;"}
+            ]
+        }"#
+        .parse::<World>();
+        let Err(WorldError::Keys(errors)) = result else {
+            panic!("unknown keys and syntax errors in scripts must be reported");
+        };
+        assert_eq!(errors.len(), 2);
+        assert_eq!(
+            errors[0],
+            KeyError::Unknown {
+                context: "event 'ring'".to_string(),
+                kind: "room",
+                key: "cellar".to_string()
+            }
+        );
+        assert!(matches!(&errors[1], KeyError::Script { error, .. } if error.column == 5));
+    }
+}
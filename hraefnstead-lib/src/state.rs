@@ -2,28 +2,54 @@ use crate::actor::Actor;
 use crate::command::Command;
 use crate::condition::Condition;
 use crate::direction::Direction;
+use crate::door::{Door, DoorState};
 use crate::entity::Entity;
+use crate::history::History;
 //use crate::event::Dialog;
-use crate::event::Event;
+use crate::id::{ActorId, DoorId, EntityId, EventId, Object, RoomId};
+use crate::locale::Locale;
+use crate::parser::{
+    alternatives, best_match, best_matches, closest, match_length, normalize, parse,
+    split_commands, without_article, ParseError, ParseErrorKind, ParsedCommand,
+};
+use crate::progress::Progress;
 use crate::room::Room;
+use crate::save::{SaveError, SaveGame};
+use crate::verb::{Verb, Vocabulary};
+use crate::victory::Victory;
+use crate::world::{World, WorldError};
 
 use std::collections::{HashMap, HashSet};
+use std::path::Path;
 
-use serde::{Deserialize, Serialize};
+/// The kinds of words standing for objects the player referred to before,
+/// see [`Vocabulary::pronouns`].
+const PRONOUNS: [&str; 3] = ["it", "them", "him"];
 
-#[derive(Debug, Serialize, Deserialize, Clone)]
+#[derive(Debug, Clone)]
 pub struct State {
-    loc: usize,
-    inventory: HashSet<usize>,
-    craft_inventory: HashMap<usize, usize>,
-    rooms: Vec<Room>,
-    entities: HashMap<usize, Entity>,
-    actors: HashMap<usize, Actor>,
-    active_events: HashSet<usize>,
-    events: Vec<Event>,
-    //dialogs: Vec<Dialog>,
-    conditions: Vec<Condition>,
+    world: World,
+    progress: Progress,
     log: String,
+    /// The question asked about an ambiguous object, awaiting an answer.
+    question: Option<Question>,
+    /// The objects the player chose when answering questions, by phrase.
+    meanings: Vec<(String, Object)>,
+    /// The objects of the last command that named any, for pronouns.
+    referents: Vec<Object>,
+    history: History,
+    /// The verbs and words of the game's locale.
+    vocabulary: Vocabulary,
+}
+
+/// A command waiting for the player to tell which object a phrase means.
+#[derive(Debug, Clone)]
+struct Question {
+    /// The command as the player typed it, for the history.
+    input: String,
+    command: Command,
+    phrase: String,
+    candidates: Vec<Object>,
 }
 impl Default for State {
     fn default() -> Self {
@@ -31,249 +57,449 @@ impl Default for State {
     }
 }
 impl State {
-    /// Create a very simple game state for testing.
+    /// Create the game state of the bundled default dungeon.
     pub fn new() -> State {
-        let mut exits_entrance = HashMap::new();
-        exits_entrance.insert(Direction::North, 1);
-        let mut exits_corridor = HashMap::new();
-        exits_corridor.insert(Direction::South, 0);
-        exits_corridor.insert(Direction::East, 2);
-        let mut exits_chamber = HashMap::new();
-        exits_chamber.insert(Direction::West, 1);
-        let mut exits_treasure_room = HashMap::new();
-        exits_treasure_room.insert(Direction::South, 2);
-
-        let mut treasure_aliases = HashSet::new();
-        treasure_aliases.insert("pile".to_string());
-        treasure_aliases.insert("stack".to_string());
-        treasure_aliases.insert("gold".to_string());
-        let treasure = Entity::new(
-            "A stack of gold",
-            "It consists for roughly some hundred shiny gold coins.",
-            treasure_aliases,
-        );
+        State::from_world(World::default())
+    }
 
-        let mut copper_aliases = HashSet::new();
-        copper_aliases.insert("copper".to_string());
-        copper_aliases.insert("coin".to_string());
-        let coin = Entity::new(
-            "A copper coin",
-            "An old, dirty copper coin.",
-            copper_aliases,
-        );
+    /// Create a fresh game state starting at the beginning of the given world.
+    pub fn from_world(world: World) -> State {
+        let progress = Progress::new(&world);
+        State::from_progress(world, progress)
+    }
 
-        let mut vending_machine_aliases = HashSet::new();
-        vending_machine_aliases.insert("machine".to_string());
-        vending_machine_aliases.insert("vending".to_string());
-        let vending_machine = Entity::new("A vending machine", "The vending machine has a small slid for the coins. The display is too dirty to reveal what it sells.", vending_machine_aliases);
-
-        let mut bag_of_chips_aliases = HashSet::new();
-        bag_of_chips_aliases.insert("chips".to_string());
-        let bag_of_chips = Entity::new(
-            "Bag of chips",
-            "The chips don't really look that bad, the smell however, suggests otherwise",
-            bag_of_chips_aliases,
-        );
+    /// Continue a game in the given world from a previously saved progress.
+    pub fn from_progress(world: World, progress: Progress) -> State {
+        let vocabulary = world.vocabulary(progress.locale.unwrap_or(world.locale));
+        Self {
+            vocabulary,
+            world,
+            progress,
+            log: String::new(),
+            question: None,
+            meanings: Vec::new(),
+            referents: Vec::new(),
+            history: History::default(),
+        }
+    }
 
-        let mut golden_armor_aliases = HashSet::new();
-        golden_armor_aliases.insert("armor".to_string());
-        let golden_armor = Entity::new(
-            "armor",
-            "A really shiny, yet very powerful piece of armor",
-            golden_armor_aliases,
-        );
-        // golden_armor_aliases.insert("golden armor".to_string());
-        let mut dead_goblin_aliases = HashSet::new();
-        dead_goblin_aliases.insert("goblin".to_string());
-        dead_goblin_aliases.insert("corpse".to_string());
-        let dead_goblin = Entity::new(
-            "Goblin corpse",
-            "The corpse smells badly and is rotting slowly.",
-            dead_goblin_aliases,
-        );
+    /// Create a fresh game state from a world definition file.
+    pub fn from_world_file<P: AsRef<Path>>(path: P) -> Result<State, WorldError> {
+        Ok(State::from_world(World::from_file(path)?))
+    }
 
-        let mut entity_map = HashMap::new();
-        entity_map.insert(1, treasure);
-        entity_map.insert(2, coin);
-        entity_map.insert(3, vending_machine);
-        entity_map.insert(4, bag_of_chips);
-        entity_map.insert(5, golden_armor);
-        entity_map.insert(6, dead_goblin);
-
-        let mut actors_map = HashMap::new();
-        let mut goblin_aliases = HashSet::new();
-        goblin_aliases.insert("goblin".to_string());
-        actors_map.insert(
-            1,
-            Actor::new(
-                "Goblin",
-                "A small red goblin leans against a door to the north.",
-                goblin_aliases,
-            ),
-        );
+    pub fn get_world(&self) -> &World {
+        &self.world
+    }
 
-        let mut chamber_actors = HashSet::new();
-        chamber_actors.insert(1);
-
-        let mut treasure_entities = HashSet::new();
-        treasure_entities.insert(1);
-
-        let mut entrance_entities = HashSet::new();
-        entrance_entities.insert(3);
-
-        let mut craft_inventory: HashMap<usize, usize> = HashMap::new();
-        craft_inventory.insert(1, 5);
-
-        let conditions = vec![
-            Condition::Location(2),
-            Condition::CommandIs(Command::Examine("bed".to_string())),
-            Condition::And(0, 1),
-            Condition::ObjectInInventory(2),
-            Condition::Location(0),
-            Condition::CommandIs(Command::Use("coin".to_string())),
-            Condition::And(3, 4),
-            Condition::And(5, 6),
-            Condition::ObjectInInventory(4),
-            Condition::CommandIs(Command::Use("chips".to_string())),
-            Condition::And(9, 8),
-            Condition::And(10, 0),
-            Condition::CommandIs(Command::Attack("goblin".to_string())),
-            Condition::Actor(1),
-            Condition::And(12, 13),
-            Condition::And(14, 0),
-            Condition::ObjectInInventory(5),
-            Condition::CommandIs(Command::Craft("gold".to_string())),
-            Condition::And(16, 17),
-        ];
-
-        let events = vec![
-            Event::new(2,
-                "The bed is made of soft wood and has a comfortable mattress. Below the pillow you find a copper coin".to_string(),
-                vec![Command::AddItemToRoom(2), Command::DeActivateEvent(0), Command::ActivateEvent(1)]),
-            Event::new(2,
-                "Now that you have taken the coin, you glance down at an empty bed".to_string(),
-                vec![]),
-            Event::new(7,
-            "The vending machine makes some concerning noice... but it works!".to_string(),
-            vec![Command::DeActivateEvent(2), Command::ActivateEvent(3), Command::AddItemToRoom(4), Command::Consume(2), Command::ActivateEvent(4)]),
-            Event::new(7,
-                "You would sure like to get more loot, however your only coin is now gone".to_string(),
-                vec![]),
-            Event::new(11,
-                "The goblin doesn't seem to take much interest in you, but he hungrily takes the chips.\nThe goblins face turns green, than grey.\nHe falls to the floow and doesn't move anymore.".to_string(),
-                vec![Command::AddExit(Direction::North, 3), Command::RemoveActor(1), Command::AddItemToRoom(6)]),
-            Event::new(15,
-                "The Goblin's Fist hits (you) like a truck and lands you on the ground, where you get knocked out".to_string(),
-                vec![Command::GameOver]),
-            // Event::new(18,
-            //     "!!!Congratulations you crafted the golden armor and won the game!!!".to_string(),
-            //     vec![Command::Won] ),
-        ];
-
-        //let dialogs = vec![];
-
-        let mut active_events = HashSet::new();
-        active_events.insert(0);
-        active_events.insert(2);
-        active_events.insert(5);
-        // active_events.insert(6);
+    pub fn get_progress(&self) -> &Progress {
+        &self.progress
+    }
 
-        Self {
-            loc: 0,
-            rooms: vec![
-                Room::new(
-                    "Entrance",
-                    "You are in the entrance of the dungeon.",
-                    entrance_entities,
-                    HashSet::new(),
-                    exits_entrance,
-                ),
-                Room::new(
-                    "Corridor",
-                    "You are in a dark corridor.",
-                    HashSet::new(),
-                    HashSet::new(),
-                    exits_corridor,
-                ),
-                Room::new(
-                    "Chamber",
-                    "There is a bed in the chamber. The pillows make a soft and inviting impression.",
-                    HashSet::new(),
-                    chamber_actors,
-                    exits_chamber,
-                ),
-                Room::new(
-                    "Treasure Room",
-                    "You found the treasure room!",
-                    treasure_entities,
-                    HashSet::new(),
-                    exits_treasure_room,
-                ),
-            ],
-            inventory: HashSet::new(),
-            craft_inventory,
-            entities: entity_map,
-            actors: actors_map,
-            active_events,
-            events,
-            //dialogs,
-            conditions,
-            log: String::new(),
-        }
+    pub fn get_vocabulary(&self) -> &Vocabulary {
+        &self.vocabulary
+    }
+
+    /// The locale of the game, the one the player picked or the world's.
+    pub fn locale(&self) -> Locale {
+        self.vocabulary.locale()
+    }
+
+    /// Switch the game to another locale, which is kept in saves.
+    pub fn set_locale(&mut self, locale: Locale) {
+        self.progress.locale = Some(locale);
+        self.vocabulary = self.world.vocabulary(locale);
+    }
+
+    /// The message with the id in the game's locale, see [`Locale::format`].
+    pub fn message(&self, id: &str, args: &[(&str, &str)]) -> String {
+        self.locale().format(id, args)
+    }
+
+    /// Serialize the progress of the game into a save file, the timestamp is
+    /// given in seconds since the unix epoch.
+    pub fn save(&self, timestamp: u64) -> Result<String, SaveError> {
+        SaveGame::new(&self.world, self.progress.clone(), timestamp).to_json()
+    }
+
+    /// Restore a game saved with [`State::save`] in the given world.
+    pub fn load(world: World, save: &str) -> Result<State, SaveError> {
+        let save = SaveGame::from_json(save, &world)?;
+        Ok(State::from_progress(world, save.progress))
     }
 
     pub fn get_room(&self) -> &Room {
-        &self.rooms[self.loc]
+        &self.world.rooms[self.progress.loc.0]
+    }
+
+    /// The description of the current room, unless a script replaced it.
+    pub fn get_room_description(&self) -> &str {
+        match self.progress.room_descriptions.get(&self.progress.loc) {
+            Some(description) => description,
+            None => self.get_room().get_description(),
+        }
     }
 
-    pub fn get_room_mut(&mut self) -> &mut Room {
-        &mut self.rooms[self.loc]
+    pub fn set_room_description(&mut self, room: RoomId, description: &str) {
+        self.progress
+            .room_descriptions
+            .insert(room, description.to_string());
     }
 
-    pub fn get_exit(&self, dir: Direction) -> Option<usize> {
-        self.rooms[self.loc].get_exit(dir)
+    /// The exits of the current room, including the ones opened during the game.
+    pub fn get_exits(&self) -> HashMap<Direction, RoomId> {
+        self.get_exits_of(self.progress.loc)
     }
 
-    pub fn set_location(&mut self, new_room: usize) {
-        self.loc = new_room;
+    /// The exits of a room, including the ones opened during the game.
+    pub fn get_exits_of(&self, loc: RoomId) -> HashMap<Direction, RoomId> {
+        let mut exits = self.world.rooms[loc.0].get_exits().clone();
+        if let Some(added_exits) = self.progress.added_exits.get(&loc) {
+            exits.extend(added_exits.iter().map(|(dir, room)| (dir.clone(), *room)));
+        }
+        exits
     }
 
-    pub fn get_from_inventory(&mut self, thing: &str) -> Option<(usize, &Entity)> {
-        let mut found_entity = None;
-        for id in self.inventory.iter() {
-            if let Some(entity) = self.entities.get(id) {
-                if entity.aliases.contains(thing) {
-                    found_entity = Some((*id, entity));
-                }
-            }
+    pub fn get_exit(&self, dir: Direction) -> Option<RoomId> {
+        let loc = self.progress.loc;
+        self.progress
+            .added_exits
+            .get(&loc)
+            .and_then(|exits| exits.get(&dir).copied())
+            .or_else(|| self.world.rooms[loc.0].get_exit(dir))
+    }
+
+    pub fn add_exit(&mut self, direction: Direction, room_number: RoomId) {
+        self.progress
+            .added_exits
+            .entry(self.progress.loc)
+            .or_default()
+            .insert(direction, room_number);
+    }
+
+    /// The name of a direction as shown to the player.
+    pub fn direction_name(&self, direction: &Direction) -> String {
+        match direction {
+            Direction::Named(name) => name.clone(),
+            direction => self.message(&format!("direction_{direction}"), &[]),
         }
-        if let Some((id, entity)) = found_entity {
-            if self.inventory.remove(&id) {
-                Some((id, entity))
-            } else {
-                None
+    }
+
+    pub fn get_door(&self, door_id: DoorId) -> Option<&Door> {
+        self.world.doors.get(door_id.0)
+    }
+
+    /// The state of a door, as changed during the game.
+    pub fn door_state(&self, door_id: DoorId) -> DoorState {
+        self.progress
+            .doors
+            .get(&door_id)
+            .copied()
+            .or_else(|| self.get_door(door_id).map(|door| door.state))
+            .unwrap_or_default()
+    }
+
+    pub fn set_door_state(&mut self, door_id: DoorId, door_state: DoorState) {
+        self.progress.doors.insert(door_id, door_state);
+    }
+
+    /// The doors of the current room with the exits they stand in, in the
+    /// order of the world.
+    pub fn get_room_doors(&self) -> Vec<(DoorId, Direction)> {
+        let loc = self.progress.loc;
+        self.world
+            .doors
+            .iter()
+            .enumerate()
+            .filter_map(|(id, door)| Some((DoorId(id), door.exit_in(loc)?.clone())))
+            .collect()
+    }
+
+    /// The door standing in an exit of the current room.
+    pub fn door_at(&self, direction: &Direction) -> Option<DoorId> {
+        self.get_room_doors()
+            .into_iter()
+            .find_map(|(id, dir)| (dir == *direction).then_some(id))
+    }
+
+    /// The door of the current room that best matches the phrase.
+    pub fn find_door(&self, thing: &str) -> Option<DoorId> {
+        match self.meaning_of(thing) {
+            Some(Object::Door(meant)) => return Some(meant),
+            Some(_) => return None,
+            None => {}
+        }
+        best_match(
+            self.get_room_doors()
+                .into_iter()
+                .map(|(id, _)| (id, self.object_match(Object::Door(id), thing))),
+        )
+    }
+
+    pub fn get_room_entities(&self) -> &HashSet<EntityId> {
+        let loc = self.progress.loc;
+        self.progress
+            .room_entities
+            .get(&loc)
+            .unwrap_or_else(|| self.world.rooms[loc.0].get_entities())
+    }
+
+    fn get_room_entities_mut(&mut self) -> &mut HashSet<EntityId> {
+        let loc = self.progress.loc;
+        let rooms = &self.world.rooms;
+        self.progress
+            .room_entities
+            .entry(loc)
+            .or_insert_with(|| rooms[loc.0].get_entities().clone())
+    }
+
+    pub fn add_entity_to_room(&mut self, entity_id: EntityId) {
+        self.get_room_entities_mut().insert(entity_id);
+    }
+
+    pub fn get_room_actors(&self) -> &HashSet<ActorId> {
+        let loc = self.progress.loc;
+        self.progress
+            .room_actors
+            .get(&loc)
+            .unwrap_or_else(|| self.world.rooms[loc.0].get_actors())
+    }
+
+    pub fn remove_actor_from_room(&mut self, actor_id: ActorId) -> bool {
+        let loc = self.progress.loc;
+        let rooms = &self.world.rooms;
+        self.progress
+            .room_actors
+            .entry(loc)
+            .or_insert_with(|| rooms[loc.0].get_actors().clone())
+            .remove(&actor_id)
+    }
+
+    pub fn set_location(&mut self, new_room: RoomId) {
+        self.progress.loc = new_room;
+        if new_room != super::GAME_OVER {
+            self.progress.visited.insert(new_room);
+        }
+    }
+
+    /// Whether the player has been in the room, saves from before visited
+    /// rooms were recorded only know the current one.
+    pub fn has_visited(&self, room: RoomId) -> bool {
+        room == self.progress.loc || self.progress.visited.contains(&room)
+    }
+
+    pub fn get_from_inventory(&mut self, thing: &str) -> Option<(EntityId, &Entity)> {
+        let id = self.find_inventory(thing)?;
+        self.progress.inventory.remove(&id);
+        self.world.entities.get(&id).map(|entity| (id, entity))
+    }
+
+    /// The entity among `ids` whose name or alias best matches the phrase,
+    /// see [`parser::match_length`].
+    fn find_entity<'a>(
+        &self,
+        mut ids: impl Iterator<Item = &'a EntityId>,
+        thing: &str,
+    ) -> Option<EntityId> {
+        match self.meaning_of(thing) {
+            Some(Object::Entity(meant)) => return ids.any(|id| *id == meant).then_some(meant),
+            Some(_) => return None,
+            None => {}
+        }
+        best_match(ids.map(|id| (*id, self.object_match(Object::Entity(*id), thing))))
+    }
+
+    /// The entity, actor or door at hand, in the inventory or the current
+    /// room, that best matches the phrase.
+    pub fn find_object(&self, thing: &str) -> Option<Object> {
+        self.meaning_of(thing)
+            .or_else(|| best_match(self.objects_at_hand(thing)))
+    }
+
+    /// The objects at hand with the length of their match for the phrase.
+    fn objects_at_hand(&self, thing: &str) -> Vec<(Object, usize)> {
+        let entities = self
+            .progress
+            .inventory
+            .iter()
+            .chain(self.get_room_entities())
+            .map(|id| Object::Entity(*id));
+        let actors = self.get_room_actors().iter().map(|id| Object::Actor(*id));
+        let doors = self
+            .get_room_doors()
+            .into_iter()
+            .map(|(id, _)| Object::Door(id));
+        entities
+            .chain(actors)
+            .chain(doors)
+            .map(|object| (object, self.object_match(object, thing)))
+            .collect()
+    }
+
+    /// The name and aliases of an entity, actor or door.
+    fn object_names(&self, object: Object) -> Option<(&str, &HashSet<String>)> {
+        match object {
+            Object::Entity(id) => self
+                .world
+                .entities
+                .get(&id)
+                .map(|entity| (entity.name.as_str(), &entity.aliases)),
+            Object::Actor(id) => self
+                .world
+                .actors
+                .get(&id)
+                .map(|actor| (actor.name.as_str(), &actor.aliases)),
+            Object::Door(id) => self
+                .get_door(id)
+                .map(|door| (door.name.as_str(), &door.aliases)),
+        }
+    }
+
+    fn object_match(&self, object: Object, thing: &str) -> usize {
+        self.object_names(object)
+            .map_or(0, |(name, aliases)| match_length(thing, name, aliases))
+    }
+
+    /// The name of an object as the game refers to it in questions.
+    pub fn object_name(&self, object: Object) -> String {
+        let name = self
+            .object_names(object)
+            .map(|(name, _)| name.to_lowercase())
+            .unwrap_or_default();
+        without_article(&name).to_string()
+    }
+
+    /// The object the player chose for the phrase when asked.
+    fn meaning_of(&self, thing: &str) -> Option<Object> {
+        self.meanings
+            .iter()
+            .find(|(phrase, _)| phrase == thing)
+            .map(|(_, object)| *object)
+    }
+
+    /// The objects at hand the command can act on, with the length of their
+    /// match for the phrase. Taking and dropping only consider the room or
+    /// the inventory.
+    fn objects_in_scope(&self, command: &Command, thing: &str) -> Vec<(Object, usize)> {
+        let mut objects = self.objects_at_hand(thing);
+        objects.retain(|(object, _)| match (command, object) {
+            (Command::Take(_), Object::Entity(id)) => self.get_room_entities().contains(id),
+            (Command::Drop(_), Object::Entity(id)) => self.progress.inventory.contains(id),
+            (Command::Take(_) | Command::Drop(_), _) => false,
+            _ => true,
+        });
+        objects
+    }
+
+    /// Ask which object the player means, if a phrase of the command names
+    /// several objects at hand equally well.
+    fn ask_if_ambiguous(&mut self, input: &str, command: &Command) -> bool {
+        for thing in [command.object(), command.indirect_object()]
+            .into_iter()
+            .flatten()
+        {
+            if self.meaning_of(thing).is_some() {
+                continue;
+            }
+            let mut candidates = best_matches(self.objects_in_scope(command, thing));
+            if candidates.len() < 2 {
+                continue;
             }
-        } else {
-            None
+            candidates.sort_by_key(|object| self.object_name(*object));
+            let mut names: Vec<String> = candidates
+                .iter()
+                .map(|object| self.message("candidate", &[("name", &self.object_name(*object))]))
+                .collect();
+            let last = names.pop().unwrap_or_default();
+            let candidates_text =
+                format!("{} {} {last}", names.join(", "), self.message("or", &[]));
+            let msg = self.message("which_one", &[("candidates", &candidates_text)]);
+            self.log(&format!("\n{msg}"));
+            self.question = Some(Question {
+                input: input.to_string(),
+                command: command.clone(),
+                phrase: thing.to_string(),
+                candidates,
+            });
+            return true;
         }
+        false
     }
 
-    pub fn get_inventory(&self) -> &HashSet<usize> {
-        &self.inventory
+    /// The candidate the input picks, if it answers the question. Every word
+    /// of an answer must be part of the chosen object's name or aliases,
+    /// otherwise the input is taken as a new command.
+    fn answer(&self, question: &Question, input: &str) -> Option<Object> {
+        let answer = normalize(input, &self.vocabulary);
+        let candidates = best_matches(
+            question
+                .candidates
+                .iter()
+                .map(|object| (*object, self.object_match(*object, &answer))),
+        );
+        let chosen = match candidates.as_slice() {
+            [object] => *object,
+            _ => *candidates
+                .iter()
+                .find(|object| self.object_name(**object) == answer)?,
+        };
+        let words = self.object_words(chosen);
+        answer
+            .split_whitespace()
+            .all(|word| words.iter().any(|known| known == word))
+            .then_some(chosen)
+    }
+
+    /// The lower case words of the name and aliases of an object.
+    fn object_words(&self, object: Object) -> Vec<String> {
+        let Some((name, aliases)) = self.object_names(object) else {
+            return Vec::new();
+        };
+        aliases
+            .iter()
+            .map(String::as_str)
+            .chain([name])
+            .flat_map(str::split_whitespace)
+            .map(str::to_lowercase)
+            .collect()
+    }
+
+    /// The words naming the objects at hand, sorted and without duplicates.
+    pub fn words_at_hand(&self) -> Vec<String> {
+        let mut words: Vec<String> = self
+            .objects_at_hand("")
+            .into_iter()
+            .flat_map(|(object, _)| self.object_words(object))
+            .collect();
+        words.sort();
+        words.dedup();
+        words
+    }
+
+    pub fn get_inventory(&self) -> &HashSet<EntityId> {
+        &self.progress.inventory
+    }
+
+    pub fn add_to_inventory(&mut self, entity_id: EntityId) {
+        self.progress.inventory.insert(entity_id);
+    }
+
+    pub fn remove_from_inventory(&mut self, entity_id: EntityId) {
+        self.progress.inventory.remove(&entity_id);
     }
 
-    pub fn get_craft_inventory(&self) -> &HashMap<usize, usize> {
-        &self.craft_inventory
+    pub fn get_craft_inventory(&self) -> &HashMap<EntityId, EntityId> {
+        &self.world.craft_recipes
     }
 
     pub fn craft_help(&mut self) {
         let mut msg = String::new();
-        for e in &self.inventory {
-            if self.craft_inventory.contains_key(e) {
+        for e in &self.progress.inventory {
+            if self.world.craft_recipes.contains_key(e) {
                 if let Some(entity) = self.get_entity(*e) {
                     msg = format!("{} ---> ", entity.name);
                 }
-                if let Some(f) = self.craft_inventory.get(e) {
+                if let Some(f) = self.world.craft_recipes.get(e) {
                     if let Some(entity) = self.get_entity(*f) {
                         msg = format!("{msg}{}", entity.name);
                     }
@@ -283,104 +509,425 @@ impl State {
         self.log(&msg);
     }
 
-    pub fn get_entity(&self, entity_id: usize) -> Option<&Entity> {
-        self.entities.get(&entity_id)
+    pub fn get_entity(&self, entity_id: EntityId) -> Option<&Entity> {
+        self.world.entities.get(&entity_id)
     }
 
     pub fn take_entity_from_room(&mut self, thing: &str) -> bool {
-        let mut found_id = None;
-        for id in self.rooms[self.loc].get_entities() {
-            if let Some(entity) = self.entities.get(id) {
-                if entity.aliases.contains(thing) {
-                    found_id = Some(*id);
+        match self.find_entity(self.get_room_entities().iter(), thing) {
+            Some(id) => self.take_entity(id),
+            None => false,
+        }
+    }
+
+    /// Move an entity from the current room into the inventory.
+    pub fn take_entity(&mut self, entity_id: EntityId) -> bool {
+        if self.get_room_entities_mut().remove(&entity_id) {
+            self.progress.inventory.insert(entity_id);
+            return true;
+        }
+        false
+    }
+
+    /// The entities in the current room, except those the phrases name,
+    /// ordered by name.
+    pub fn room_entities_except(&self, except: &[String]) -> Vec<EntityId> {
+        self.entities_except(self.get_room_entities(), except)
+    }
+
+    /// The entities in the inventory, except those the phrases name, ordered
+    /// by name.
+    pub fn inventory_except(&self, except: &[String]) -> Vec<EntityId> {
+        self.entities_except(&self.progress.inventory, except)
+    }
+
+    fn entities_except(&self, ids: &HashSet<EntityId>, except: &[String]) -> Vec<EntityId> {
+        let excluded: HashSet<EntityId> = except
+            .iter()
+            .filter_map(|thing| self.find_entity(ids.iter(), thing))
+            .collect();
+        let mut entities: Vec<EntityId> = ids.difference(&excluded).copied().collect();
+        entities.sort_by_key(|id| self.world.entities.get(id).map(|entity| &entity.name));
+        entities
+    }
+
+    pub fn get_actor(&self, actor_id: ActorId) -> Option<&Actor> {
+        self.world.actors.get(&actor_id)
+    }
+
+    /// Parse and execute a line of player input, which may hold several
+    /// commands. The chain stops at the first command that is not
+    /// understood, that names an object ambiguously or that ends the turn
+    /// with a victory, e.g. the player's death or a request to save. An
+    /// ambiguous command waits for the next input to tell which object the
+    /// player means.
+    pub fn play(&mut self, input: &str) -> Victory {
+        if let Some(question) = self.question.take() {
+            if let Some(object) = self.answer(&question, input) {
+                self.meanings.push((question.phrase, object));
+                if self.ask_if_ambiguous(&question.input, &question.command) {
+                    return Victory::None;
                 }
+                let command = question
+                    .command
+                    .map_objects(|thing| match self.meaning_of(thing) {
+                        Some(object) => self.object_name(object),
+                        None => thing.to_string(),
+                    });
+                self.remember(&question.input, command);
+                let victory = self.run(&question.command);
+                self.meanings.clear();
+                return victory;
             }
+            self.meanings.clear();
+        }
+        if self.is_dead() {
+            self.log(&format!("\n{}", self.message("still_dead", &[])));
+            return Victory::None;
+        }
+        let commands = split_commands(input, &self.vocabulary);
+        if commands.is_empty() {
+            self.log(&self.parse_error_message(&ParseError {
+                kind: ParseErrorKind::EmptyInput,
+                span: 0..input.len(),
+            }));
         }
-        if let Some(id) = found_id {
-            if self.rooms[self.loc].remove_entity(id) {
-                self.inventory.insert(id);
-                return true;
+        for typed in commands {
+            let inputs = match self.substitute_pronouns(&typed) {
+                Ok(inputs) => inputs,
+                Err(pronoun) => {
+                    let msg = self.message("unknown_pronoun", &[("pronoun", &pronoun)]);
+                    self.log(&format!("\n{msg}"));
+                    break;
+                }
+            };
+            // A command repeated for each of "them" keeps referring to all.
+            let group = (inputs.len() > 1).then(|| self.referents.clone());
+            for input in inputs {
+                let command = match parse(&input, &self.vocabulary) {
+                    Ok(parsed) => self.understand(parsed),
+                    Err(error) => {
+                        self.log(&self.parse_error_message(&error));
+                        return Victory::None;
+                    }
+                };
+                if self.ask_if_ambiguous(&typed, &command) {
+                    return Victory::None;
+                }
+                self.remember(&typed, command.clone());
+                let victory = self.run(&command);
+                if victory != Victory::None || self.is_dead() {
+                    return victory;
+                }
+            }
+            if let Some(group) = group {
+                self.referents = group;
             }
         }
-        false
+        Victory::None
     }
 
-    pub fn get_actor(&self, actor_id: usize) -> Option<&Actor> {
-        self.actors.get(&actor_id)
+    /// Add the input as the player typed it and the command it resolved to
+    /// to the history, unless it repeats or lists the history.
+    fn remember(&mut self, input: &str, command: Command) {
+        if !matches!(command, Command::Again | Command::History) {
+            self.history.push(input, command);
+        }
     }
 
-    pub fn special_event_triggered(&mut self, command: &Command) -> Option<Vec<Command>> {
-        let mut msg = String::new();
-        let mut command_stack = None;
-        for event_id in self.active_events.iter() {
-            let event = &self.events[*event_id];
-            let condition = &self.conditions[event.condition_id];
-            if self.check_condition(condition, command) {
-                msg = format!("{}\n", event.message);
-                command_stack = Some(event.command_stack.clone());
-                break;
+    /// Replace the pronouns of a command by the names of the objects the player
+    /// last referred to: "it" stands for a thing, "him" and "her" for an actor
+    /// and "them" for all of the objects, which repeats the command for each.
+    /// Fails with the pronoun if there is nothing it could refer to.
+    fn substitute_pronouns(&self, input: &str) -> Result<Vec<String>, String> {
+        let normalized = normalize(input, &self.vocabulary);
+        let words: Vec<&str> = normalized.split_whitespace().collect();
+        let has_pronoun = words
+            .iter()
+            .skip(1)
+            .any(|word| self.pronoun(word).is_some());
+        let keeps_case = words
+            .first()
+            .and_then(|word| self.vocabulary.find(word))
+            .is_some_and(|verb| matches!(verb.name.as_str(), "save" | "load"));
+        if !has_pronoun || keeps_case {
+            return Ok(vec![input.to_string()]);
+        }
+        let mut inputs = vec![String::new()];
+        for (position, word) in words.iter().enumerate() {
+            let pronoun = self.pronoun(word).filter(|_| position > 0);
+            let replacements: Vec<String> = if let Some(pronoun) = pronoun {
+                let objects = pronoun_referents(pronoun, &self.referents);
+                if objects.is_empty() {
+                    return Err(word.to_string());
+                }
+                objects
+                    .into_iter()
+                    .map(|object| self.object_name(object))
+                    .collect()
+            } else {
+                vec![word.to_string()]
+            };
+            inputs = inputs
+                .iter()
+                .flat_map(|input| {
+                    replacements
+                        .iter()
+                        .map(move |word| format!("{input} {word}").trim_start().to_string())
+                })
+                .collect();
+        }
+        Ok(inputs)
+    }
+
+    /// The kind of pronoun the word is, if it is one.
+    fn pronoun(&self, word: &str) -> Option<&'static str> {
+        PRONOUNS.into_iter().find(|pronoun| {
+            self.vocabulary
+                .pronouns(pronoun)
+                .iter()
+                .any(|known| known == word)
+        })
+    }
+
+    /// The parsed command with its misspelled objects corrected, noting the
+    /// corrections for the player.
+    fn understand(&mut self, parsed: ParsedCommand) -> Command {
+        if let Some((typed, verb)) = parsed.correction {
+            self.log(&self.message("taking_to_mean", &[("typed", &typed), ("meant", &verb)]));
+        }
+        self.correct_objects(parsed.command)
+    }
+
+    /// Correct misspelled words in the objects of a command that are close
+    /// to exactly one word naming an object at hand. A word close to several
+    /// is not corrected, but the candidates are suggested.
+    fn correct_objects(&mut self, command: Command) -> Command {
+        if command.object().is_none() {
+            return command;
+        }
+        let known = self.words_at_hand();
+        let or = self.message("or", &[]);
+        let mut notes = Vec::new();
+        let command = command.map_objects(|phrase| {
+            let words: Vec<&str> = phrase
+                .split_whitespace()
+                .map(|word| {
+                    if known.iter().any(|known| known == word) {
+                        return word;
+                    }
+                    match closest(word, known.iter().map(String::as_str)).as_slice() {
+                        [correction] => {
+                            notes.push(self.message(
+                                "taking_to_mean",
+                                &[("typed", word), ("meant", correction)],
+                            ));
+                            correction
+                        }
+                        [] => word,
+                        candidates => {
+                            notes.push(self.message(
+                                "could_mean",
+                                &[
+                                    ("word", word),
+                                    ("candidates", &alternatives(candidates, &or)),
+                                ],
+                            ));
+                            word
+                        }
+                    }
+                })
+                .collect();
+            words.join(" ")
+        });
+        for note in notes {
+            self.log(&note);
+        }
+        command
+    }
+
+    /// The message telling the player why their input was not understood.
+    /// The message of the verb for a missing object, chosen by `id`.
+    fn missing_object_message(&self, verb: &str, id: impl Fn(&Verb) -> &String) -> String {
+        match self
+            .vocabulary
+            .verbs()
+            .iter()
+            .find(|known| known.name == verb)
+        {
+            Some(known) => {
+                let word = known.words.first().map_or(verb, String::as_str);
+                self.message(id(known), &[("verb", word)])
             }
+            None => self.message("missing_object", &[("verb", verb)]),
         }
-        self.log(&msg);
-        command_stack
     }
 
-    pub fn check_condition(&self, condition: &Condition, command: &Command) -> bool {
-        match condition {
-            Condition::Location(loc) => self.loc == *loc,
-            Condition::And(c1, c2) => {
-                self.check_condition(&self.conditions[*c1], command)
-                    && self.check_condition(&self.conditions[*c2], command)
+    fn parse_error_message(&self, error: &ParseError) -> String {
+        match &error.kind {
+            ParseErrorKind::EmptyInput => format!("\n{}", self.message("beg_pardon", &[])),
+            ParseErrorKind::UnknownVerb(suggestions) if suggestions.is_empty() => {
+                self.message("unknown_verb", &[])
             }
-            Condition::Actor(actor_id) => self.actors.contains_key(actor_id),
-            Condition::CommandIs(command_condition) => command_condition == command,
-            Condition::ObjectInInventory(entity_id) => self.inventory.contains(entity_id),
-            Condition::Or(c1, c2) => {
-                self.check_condition(&self.conditions[*c1], command)
-                    || self.check_condition(&self.conditions[*c2], command)
+            ParseErrorKind::UnknownVerb(suggestions) => {
+                let suggestions: Vec<&str> = suggestions.iter().map(String::as_str).collect();
+                let or = self.message("or", &[]);
+                self.message(
+                    "did_you_mean",
+                    &[("suggestions", &alternatives(&suggestions, &or))],
+                )
             }
-            Condition::NotLocation(loc) => self.loc != *loc,
-            Condition::NotOr(c1, c2) => {
-                !self.check_condition(&self.conditions[*c1], command)
-                    && !self.check_condition(&self.conditions[*c2], command)
+            ParseErrorKind::MissingDirection => self.message("missing_direction", &[]),
+            ParseErrorKind::UnknownDirection(_) => self.message("unknown_direction", &[]),
+            ParseErrorKind::MissingObject(verb) => {
+                self.missing_object_message(verb, |known| &known.missing_object)
             }
-            Condition::NotCommandIs(command_condition) => command_condition != command,
-            Condition::NotObjectInInventory(entity_id) => !self.inventory.contains(entity_id),
-            Condition::NotAnd(c1, c2) => {
-                !self.check_condition(&self.conditions[*c1], command)
-                    || !self.check_condition(&self.conditions[*c2], command)
+            ParseErrorKind::MissingIndirectObject(verb) => {
+                self.missing_object_message(verb, |known| &known.missing_indirect_object)
             }
         }
     }
 
-    pub fn de_activate_event(&mut self, event_id: &usize) {
-        self.active_events.remove(event_id);
+    /// The inputs of this session with the commands they were parsed into.
+    pub fn get_history(&self) -> &History {
+        &self.history
     }
-    pub fn activate_event(&mut self, event_id: &usize) {
-        self.active_events.insert(*event_id);
+
+    /// The objects the player last referred to, for pronouns like "it".
+    pub fn get_referents(&self) -> &[Object] {
+        &self.referents
     }
-    pub fn find_inventory(&mut self, thing: &str) -> Option<usize> {
-        for id in self.inventory.iter() {
-            if let Some(entity) = self.entities.get(id) {
-                if entity.aliases.contains(thing) {
-                    return Some(*id);
-                }
+
+    /// The objects a command refers to, resolved before it changes where
+    /// they are.
+    fn referents_of(&self, command: &Command) -> Vec<Object> {
+        let entities = match command {
+            Command::TakeAll(except) => self.room_entities_except(except),
+            Command::DropAll(except) => self.inventory_except(except),
+            _ => {
+                return [command.object(), command.indirect_object()]
+                    .into_iter()
+                    .flatten()
+                    .filter_map(|thing| {
+                        self.meaning_of(thing)
+                            .or_else(|| best_match(self.objects_in_scope(command, thing)))
+                    })
+                    .collect()
+            }
+        };
+        entities.into_iter().map(Object::Entity).collect()
+    }
+
+    /// Execute a command, or the commands of the event it triggers, and
+    /// remember the objects it refers to.
+    pub fn run(&mut self, command: &Command) -> Victory {
+        let referents = self.referents_of(command);
+        let victory = self.run_events(command);
+        if !referents.is_empty() {
+            self.referents = referents;
+        }
+        victory
+    }
+
+    fn run_events(&mut self, command: &Command) -> Victory {
+        let Some(command_stack) = self.special_event_triggered(command) else {
+            return command.execute(self);
+        };
+        for command in command_stack {
+            let victory = command.execute(self);
+            if victory != Victory::None {
+                return victory;
+            }
+        }
+        Victory::None
+    }
+
+    /// The active event whose condition the command meets, if any.
+    pub fn triggered_event(&self, command: &Command) -> Option<EventId> {
+        self.progress
+            .active_events
+            .iter()
+            .copied()
+            .find(|event_id| {
+                self.check_condition(&self.world.events[event_id.0].condition, command)
+            })
+    }
+
+    pub fn special_event_triggered(&mut self, command: &Command) -> Option<Vec<Command>> {
+        let Some(event_id) = self.triggered_event(command) else {
+            self.log("");
+            return None;
+        };
+        let event = &self.world.events[event_id.0];
+        let msg = format!("{}\n", event.message);
+        let command_stack = event.command_stack.clone();
+        self.log(&msg);
+        Some(command_stack)
+    }
+
+    pub fn check_condition(&self, condition: &Condition, command: &Command) -> bool {
+        match condition {
+            Condition::Location(loc) => self.progress.loc == *loc,
+            Condition::Actor(actor_id) => self.world.actors.contains_key(actor_id),
+            Condition::ActorHere(actor_id) => self.get_room_actors().contains(actor_id),
+            Condition::CommandIs(command_condition) => {
+                command_condition == command || self.same_object(command_condition, command)
             }
+            Condition::ObjectInInventory(entity_id) => self.progress.inventory.contains(entity_id),
+            Condition::All(conditions) => conditions
+                .iter()
+                .all(|condition| self.check_condition(condition, command)),
+            Condition::Any(conditions) => conditions
+                .iter()
+                .any(|condition| self.check_condition(condition, command)),
+            Condition::Not(condition) => !self.check_condition(condition, command),
+            Condition::Script(expr) => expr.eval(self),
         }
-        None
     }
-    pub fn consume_from_inventory(&mut self, id: &usize) {
-        self.inventory.remove(id);
+
+    /// Whether both commands do the same to the same objects, although the
+    /// player named them differently, e.g. "use copper coin" and "use coin".
+    fn same_object(&self, expected: &Command, command: &Command) -> bool {
+        if std::mem::discriminant(expected) != std::mem::discriminant(command) {
+            return false;
+        }
+        let same = |expected: Option<&str>, named: Option<&str>| match (expected, named) {
+            (None, None) => true,
+            (Some(expected), Some(named)) => {
+                expected == named
+                    || matches!(
+                        (self.find_object(expected), self.find_object(named)),
+                        (Some(a), Some(b)) if a == b
+                    )
+            }
+            _ => false,
+        };
+        expected.object().is_some()
+            && same(expected.object(), command.object())
+            && same(expected.indirect_object(), command.indirect_object())
+    }
+
+    pub fn de_activate_event(&mut self, event_id: &EventId) {
+        self.progress.active_events.remove(event_id);
+    }
+    pub fn activate_event(&mut self, event_id: &EventId) {
+        self.progress.active_events.insert(*event_id);
+    }
+    pub fn find_inventory(&self, thing: &str) -> Option<EntityId> {
+        self.find_entity(self.progress.inventory.iter(), thing)
+    }
+    pub fn consume_from_inventory(&mut self, id: &EntityId) {
+        self.progress.inventory.remove(id);
         let mut msg = String::new();
-        let removed = self.entities.get(id);
+        let removed = self.world.entities.get(id);
         if let Some(removed) = removed {
-            msg = format!("{msg}\nConsumed {}", removed.get_name());
-            println!("Consumed {}", removed.get_name());
+            let consumed = self.message("consumed", &[("thing", removed.get_name())]);
+            msg = format!("{msg}\n{consumed}");
         }
         self.log(&msg);
     }
-    pub fn why_not_mutable(&mut self, mega_id: usize) {
-        self.inventory.insert(mega_id);
+    pub fn why_not_mutable(&mut self, mega_id: EntityId) {
+        self.progress.inventory.insert(mega_id);
     }
 
     pub fn log(&mut self, msg: &str) {
@@ -393,19 +940,47 @@ impl State {
         log
     }
     pub fn reset(&mut self) {
-        let reset = State::new();
-        self.inventory = reset.inventory;
-        self.loc = reset.loc;
-        self.craft_inventory = reset.craft_inventory;
-        self.rooms = reset.rooms;
-        self.entities = reset.entities;
-        self.actors = reset.actors;
-        self.events = reset.events;
-        self.conditions = reset.conditions;
-        self.log = reset.log;
+        let locale = self.progress.locale;
+        self.progress = Progress::new(&self.world);
+        self.progress.locale = locale;
+        self.log = String::new();
+    }
+    pub fn set_flag(&mut self, flag: &str) {
+        self.progress.flags.insert(flag.to_string());
+    }
+    pub fn clear_flag(&mut self, flag: &str) {
+        self.progress.flags.remove(flag);
+    }
+    pub fn has_flag(&self, flag: &str) -> bool {
+        self.progress.flags.contains(flag)
+    }
+    pub fn get_counter(&self, counter: &str) -> i64 {
+        self.progress.counters.get(counter).copied().unwrap_or(0)
+    }
+    pub fn add_to_counter(&mut self, counter: &str, amount: i64) {
+        *self
+            .progress
+            .counters
+            .entry(counter.to_string())
+            .or_default() += amount;
     }
     pub fn is_dead(&self) -> bool {
-        self.loc == super::GAME_OVER
+        self.progress.loc == super::GAME_OVER
+    }
+}
+
+/// The objects among the referents a pronoun stands for.
+fn pronoun_referents(pronoun: &str, referents: &[Object]) -> Vec<Object> {
+    let actor = referents
+        .iter()
+        .find(|object| matches!(object, Object::Actor(_)));
+    let thing = referents
+        .iter()
+        .find(|object| matches!(object, Object::Entity(_) | Object::Door(_)));
+    match pronoun {
+        "them" => referents.to_vec(),
+        "him" => actor.into_iter().copied().collect(),
+        _ => thing.into_iter().copied().collect(),
     }
 }
 
@@ -418,15 +993,261 @@ mod tests {
     #[test]
     pub fn it_works() {
         let mut state = State::new();
-        state.inventory.insert(1);
+        state.progress.inventory.insert(EntityId(1));
         Command::Craft("gold".to_string()).execute(&mut state);
-        assert!(state.inventory.contains(&5) && !state.inventory.contains(&1))
+        assert!(
+            state.progress.inventory.contains(&EntityId(5))
+                && !state.progress.inventory.contains(&EntityId(1))
+        )
     }
 
     #[test]
     fn does_it_work() {
         let mut state = State::new();
-        state.inventory.insert(2);
+        state.progress.inventory.insert(EntityId(2));
         assert_eq!(Command::Inventory.execute(&mut state), Victory::None);
     }
+
+    #[test]
+    fn save_only_contains_progress() {
+        let mut state = State::new();
+        state.set_location(RoomId(2));
+        state.add_entity_to_room(EntityId(2));
+        state.take_entity_from_room("coin");
+        state.add_exit(Direction::North, RoomId(3));
+        let save = state.save(0).unwrap();
+        assert!(!save.contains("Chamber"));
+
+        let loaded = State::load(World::default(), &save).unwrap();
+        assert_eq!(loaded.get_progress(), state.get_progress());
+        assert!(loaded.get_inventory().contains(&EntityId(2)));
+        assert!(loaded.get_room_entities().is_empty());
+        assert_eq!(loaded.get_exit(Direction::North), Some(RoomId(3)));
+        assert_eq!(loaded.get_exit(Direction::West), Some(RoomId(1)));
+    }
+
+    #[test]
+    fn locale_is_kept_in_saves() {
+        let mut state = State::new();
+        assert_eq!(state.locale(), Locale::En);
+        state.set_locale(Locale::De);
+        state.add_entity_to_room(EntityId(2));
+        state.play("nimm den coin");
+        assert!(state.get_log().ends_with("Genommen."));
+        state.play("tanze");
+        assert!(state
+            .get_log()
+            .ends_with("Diesen Befehl verstehe ich nicht."));
+
+        let loaded = State::load(World::default(), &state.save(0).unwrap()).unwrap();
+        assert_eq!(loaded.locale(), Locale::De);
+        assert_eq!(
+            loaded.get_vocabulary().help("Default").lines().next(),
+            Some("schau (schaue, l)")
+        );
+    }
+
+    #[test]
+    fn exits_lead_up_down_diagonally_and_through_names() {
+        let world = crate::dsl::compile(
+            "Start in Hall\n\
+             Room \"Hall\": \"A hall.\"\n    exit up to Tower\n    exit \"trap door\" to Cellar\n    exit ne to Garden\n\
+             Room \"Tower\": \"A tower.\"\n    exit down to Hall\n\
+             Room \"Cellar\": \"A cellar.\"\n    exit out to Hall\n\
+             Room \"Garden\": \"A garden.\"\n    exit southwest to Hall",
+        )
+        .unwrap()
+        .resolve()
+        .unwrap();
+        let mut state = State::from_world(world);
+        state.play("look");
+        assert!(state.get_log().contains("Exits:Northeast Up trap door \n"));
+        state.play("u");
+        assert!(state.get_log().contains("A tower."));
+        state.play("go down, enter the trap door");
+        assert!(state
+            .get_log()
+            .ends_with("A cellar.\nExits:Out \n\nThere is nothing here."));
+        state.play("out. ne");
+        assert!(state.get_log().contains("A garden."));
+        state.play("map");
+        assert!(state.get_log().lines().any(|line| line.trim() == "/"));
+        state.play("go sideways");
+        assert!(state.get_log().ends_with("I don't know that direction."));
+    }
+
+    #[test]
+    fn doors_are_unlocked_with_their_key() {
+        let world = crate::dsl::compile(
+            "Start in Hall\n\
+             Room \"Hall\": \"A hall.\"\n    Thing \"A brass key\" (key): \"A key.\"\n    Thing \"A stick\" (stick): \"A stick.\"\n\
+             Room \"Vault\": \"A vault.\"\n\
+             Door \"oak door\" (door) between Hall north and Vault south locked with key",
+        )
+        .unwrap()
+        .resolve()
+        .unwrap();
+        let mut state = State::from_world(world.clone());
+        state.play("look");
+        assert!(state.get_log().contains("The oak door (North) is locked."));
+        state.play("north");
+        assert!(state.get_log().ends_with("The oak door is locked."));
+        state.play("unlock door");
+        assert!(state
+            .get_log()
+            .ends_with("You have no key for the oak door."));
+        state.play("take stick, unlock door with stick");
+        assert!(state
+            .get_log()
+            .ends_with("The stick does not fit the lock."));
+        state.play("take key. unlock door. open it. go north");
+        assert!(state
+            .get_log()
+            .ends_with("A vault.\nExits:South \n\nThere is nothing here."));
+        state.play("close door, lock door with key");
+        assert!(state.get_log().ends_with("You lock the oak door."));
+
+        let loaded = State::load(world, &state.save(0).unwrap()).unwrap();
+        assert_eq!(loaded.door_state(DoorId(0)), DoorState::Locked);
+    }
+
+    #[test]
+    fn the_removed_goblin_does_not_attack() {
+        let mut state = State::new();
+        state.set_location(RoomId(2));
+        state.remove_actor_from_room(ActorId(1));
+        state.play("attack goblin");
+        assert!(!state.is_dead());
+    }
+
+    #[test]
+    fn all_takes_every_entity_but_the_excepted() {
+        let mut state = State::new();
+        state.add_entity_to_room(EntityId(2));
+        state.add_entity_to_room(EntityId(4));
+        state.play("take all but machine");
+        assert!(state
+            .get_log()
+            .ends_with("\nA copper coin: taken.\nBag of chips: taken."));
+        assert_eq!(state.get_room_entities(), &HashSet::from([EntityId(3)]));
+
+        state.play("drop everything");
+        assert_eq!(state.get_inventory().len(), 0);
+        assert_eq!(state.get_room_entities().len(), 3);
+    }
+
+    #[test]
+    fn ambiguous_objects_are_asked_for() {
+        let mut state = State::new();
+        state.set_location(RoomId(2));
+        state.add_to_inventory(EntityId(6));
+        state.play("examine goblin then look");
+        assert!(state
+            .get_log()
+            .ends_with("Which do you mean, the goblin or the goblin corpse?"));
+        state.play("corpse");
+        assert!(state
+            .get_log()
+            .ends_with("The corpse smells badly and is rotting slowly."));
+
+        state.play("examine goblin");
+        state.play("the goblin");
+        assert!(state
+            .get_log()
+            .ends_with("You need to have item in inventory!"));
+
+        state.play("drop goblin");
+        assert!(state.get_room_entities().contains(&EntityId(6)));
+        state.play("examine goblin");
+        state.play("look");
+        assert!(state.get_log().contains("There is a bed"));
+    }
+
+    #[test]
+    fn history_keeps_typed_inputs_with_resolved_commands() {
+        let mut state = State::new();
+        state.set_location(RoomId(2));
+        state.add_to_inventory(EntityId(6));
+        state.play("examine goblin");
+        state.play("corpse");
+        state.play("again");
+        assert!(state
+            .get_log()
+            .ends_with("The corpse smells badly and is rotting slowly."));
+        state.play("examine it");
+        state.play("history");
+        assert!(state
+            .get_log()
+            .ends_with("\n1. examine goblin\n2. examine it"));
+        assert_eq!(
+            state.get_history().last().map(|entry| &entry.command),
+            Some(&Command::Examine("goblin corpse".to_string()))
+        );
+    }
+
+    #[test]
+    fn misspelled_objects_are_corrected() {
+        let mut state = State::new();
+        state.play("tkae vendign mashine");
+        assert!(state.get_log().ends_with("Taken."));
+        assert!(state.get_inventory().contains(&EntityId(3)));
+    }
+
+    #[test]
+    fn pronouns_refer_to_the_last_objects() {
+        let mut state = State::new();
+        state.add_entity_to_room(EntityId(2));
+        state.add_entity_to_room(EntityId(4));
+        state.play("take it");
+        assert!(state
+            .get_log()
+            .ends_with("I don't know what \"it\" refers to."));
+
+        state.play("take coin. drop it");
+        assert!(state.get_log().ends_with("You drop the A copper coin"));
+        state.play("take all but machine, examine them");
+        assert!(state.get_log().contains("An old, dirty copper coin."));
+        state.play("drop them");
+        assert_eq!(state.get_room_entities().len(), 3);
+        state.play("give chips to him");
+        assert!(state
+            .get_log()
+            .ends_with("I don't know what \"him\" refers to."));
+    }
+
+    #[test]
+    fn pronouns_refer_to_what_the_command_acted_on() {
+        let mut state = State::new();
+        state.set_location(RoomId(2));
+        state.add_to_inventory(EntityId(6));
+        state.play("drop goblin, take it");
+        assert!(state.get_inventory().contains(&EntityId(6)));
+
+        state.referents = vec![Object::Actor(ActorId(1))];
+        state.play("examine it");
+        assert!(state
+            .get_log()
+            .ends_with("I don't know what \"it\" refers to."));
+    }
+
+    #[test]
+    fn objects_are_found_by_the_longest_name() {
+        let mut state = State::new();
+        state.add_to_inventory(EntityId(5));
+        state.add_to_inventory(EntityId(2));
+        assert_eq!(state.find_inventory("golden armor"), Some(EntityId(5)));
+        assert_eq!(
+            state.find_inventory("the old copper coin"),
+            Some(EntityId(2))
+        );
+        assert_eq!(state.find_inventory("stone"), None);
+        assert_eq!(
+            state.find_object("vending machine"),
+            Some(Object::Entity(EntityId(3)))
+        );
+
+        let use_coin = Condition::CommandIs(Command::Use("coin".to_string()));
+        assert!(state.check_condition(&use_coin, &Command::Use("copper coin".to_string())));
+        assert!(!state.check_condition(&use_coin, &Command::Examine("coin".to_string())));
+    }
 }
@@ -0,0 +1,363 @@
+//! Consistency checks for world definitions.
+//!
+//! Everything in a [`World`] is linked by ids, so a dangling id only shows
+//! up as a panic in the middle of a game. Loading a world file already
+//! rejects unknown keys, [`validate`] also covers worlds built in code and
+//! walks the whole world up front, reporting every problem it finds.
+
+use crate::command::Command;
+use crate::condition::Condition;
+use crate::direction::Direction;
+use crate::id::{ActorId, DoorId, EntityId, EventId, RoomId};
+use crate::script::{Expr, Script, Statement};
+use crate::world::World;
+
+use std::collections::HashMap;
+use std::fmt::Display;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+/// The place in the world definition a diagnostic refers to.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Location {
+    Start,
+    Room(RoomId),
+    Exit(RoomId, Direction),
+    Door(DoorId),
+    CraftRecipe(EntityId),
+    Event(EventId),
+    /// An event and the position of the command in its command stack.
+    EventCommand(EventId, usize),
+    EventScript(EventId),
+    ActiveEvents,
+    Aliases,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Problem {
+    MissingRoom(RoomId),
+    MissingEntity(EntityId),
+    MissingActor(ActorId),
+    MissingEvent(EventId),
+    /// The alias is shared by several entities and/or actors.
+    AliasCollision(String, Vec<String>),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    pub location: Location,
+    pub problem: Problem,
+}
+
+impl Diagnostic {
+    fn error(location: Location, problem: Problem) -> Self {
+        Self {
+            severity: Severity::Error,
+            location,
+            problem,
+        }
+    }
+
+    fn warning(location: Location, problem: Problem) -> Self {
+        Self {
+            severity: Severity::Warning,
+            location,
+            problem,
+        }
+    }
+
+    pub fn is_error(&self) -> bool {
+        self.severity == Severity::Error
+    }
+}
+
+impl Display for Severity {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Severity::Error => write!(f, "error"),
+            Severity::Warning => write!(f, "warning"),
+        }
+    }
+}
+
+impl Display for Location {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Location::Start => write!(f, "start"),
+            Location::Room(room) => write!(f, "room {room}"),
+            Location::Exit(room, dir) => write!(f, "room {room}, exit {dir}"),
+            Location::Door(door) => write!(f, "door {door}"),
+            Location::CraftRecipe(entity) => write!(f, "craft recipe {entity}"),
+            Location::Event(event) => write!(f, "event {event}"),
+            Location::EventCommand(event, command) => {
+                write!(f, "event {event}, command {command}")
+            }
+            Location::EventScript(event) => write!(f, "event {event}, script"),
+            Location::ActiveEvents => write!(f, "active events"),
+            Location::Aliases => write!(f, "aliases"),
+        }
+    }
+}
+
+impl Display for Problem {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Problem::MissingRoom(id) => write!(f, "unknown room {id}"),
+            Problem::MissingEntity(id) => write!(f, "unknown entity {id}"),
+            Problem::MissingActor(id) => write!(f, "unknown actor {id}"),
+            Problem::MissingEvent(id) => write!(f, "unknown event {id}"),
+            Problem::AliasCollision(alias, owners) => {
+                write!(f, "alias '{alias}' is used by {}", owners.join(", "))
+            }
+        }
+    }
+}
+
+impl Display for Diagnostic {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}: {}: {}", self.severity, self.location, self.problem)
+    }
+}
+
+/// Check a world for dangling references and aliases shared between several
+/// objects.
+pub fn validate(world: &World) -> Vec<Diagnostic> {
+    let mut validator = Validator {
+        world,
+        diagnostics: Vec::new(),
+    };
+    validator.check_rooms();
+    validator.check_doors();
+    validator.check_craft_recipes();
+    validator.check_events();
+    validator.check_aliases();
+    validator.diagnostics
+}
+
+struct Validator<'a> {
+    world: &'a World,
+    diagnostics: Vec<Diagnostic>,
+}
+
+impl Validator<'_> {
+    fn room(&mut self, location: Location, room: RoomId) {
+        if room.0 >= self.world.rooms.len() {
+            self.diagnostics
+                .push(Diagnostic::error(location, Problem::MissingRoom(room)));
+        }
+    }
+
+    fn entity(&mut self, location: Location, entity: EntityId) {
+        if !self.world.entities.contains_key(&entity) {
+            self.diagnostics
+                .push(Diagnostic::error(location, Problem::MissingEntity(entity)));
+        }
+    }
+
+    fn actor(&mut self, location: Location, actor: ActorId) {
+        if !self.world.actors.contains_key(&actor) {
+            self.diagnostics
+                .push(Diagnostic::error(location, Problem::MissingActor(actor)));
+        }
+    }
+
+    fn event(&mut self, location: Location, event: EventId) {
+        if event.0 >= self.world.events.len() {
+            self.diagnostics
+                .push(Diagnostic::error(location, Problem::MissingEvent(event)));
+        }
+    }
+
+    fn command(&mut self, location: Location, command: &Command) {
+        match command {
+            Command::AddItemToRoom(entity) | Command::Consume(entity) => {
+                self.entity(location, *entity)
+            }
+            Command::ActivateEvent(event) | Command::DeActivateEvent(event) => {
+                self.event(location, *event)
+            }
+            Command::RemoveActor(actor) => self.actor(location, *actor),
+            Command::AddExit(_, room) => self.room(location, *room),
+            Command::RunScript(event) => self.event(location, *event),
+            _ => {}
+        }
+    }
+
+    fn check_rooms(&mut self) {
+        self.room(Location::Start, self.world.start);
+        for (room_id, room) in self.world.rooms.iter().enumerate() {
+            let room_id = RoomId(room_id);
+            for entity in room.get_entities() {
+                self.entity(Location::Room(room_id), *entity);
+            }
+            for actor in room.get_actors() {
+                self.actor(Location::Room(room_id), *actor);
+            }
+            for (dir, target) in room.get_exits() {
+                self.room(Location::Exit(room_id, dir.clone()), *target);
+            }
+        }
+    }
+
+    fn check_doors(&mut self) {
+        for (door_id, door) in self.world.doors.iter().enumerate() {
+            let door_id = DoorId(door_id);
+            for (room, _) in door.sides.iter() {
+                self.room(Location::Door(door_id), *room);
+            }
+            if let Some(key) = door.key {
+                self.entity(Location::Door(door_id), key);
+            }
+        }
+    }
+
+    fn check_craft_recipes(&mut self) {
+        for (ingredient, product) in self.world.craft_recipes.iter() {
+            self.entity(Location::CraftRecipe(*ingredient), *ingredient);
+            self.entity(Location::CraftRecipe(*ingredient), *product);
+        }
+    }
+
+    fn condition(&mut self, location: Location, condition: &Condition) {
+        match condition {
+            Condition::CommandIs(command) => self.command(location, command),
+            Condition::Location(room) => self.room(location, *room),
+            Condition::ObjectInInventory(entity) => self.entity(location, *entity),
+            Condition::Actor(actor) | Condition::ActorHere(actor) => self.actor(location, *actor),
+            Condition::All(conditions) | Condition::Any(conditions) => {
+                for condition in conditions {
+                    self.condition(location.clone(), condition);
+                }
+            }
+            Condition::Not(condition) => self.condition(location, condition),
+            Condition::Script(expr) => self.expr(location, expr),
+        }
+    }
+
+    fn expr(&mut self, location: Location, expr: &Expr) {
+        match expr {
+            Expr::Has(entity) | Expr::Here(entity) => self.entity(location, *entity),
+            Expr::At(room) => self.room(location, *room),
+            Expr::Not(expr) => self.expr(location, expr),
+            Expr::And(left, right) | Expr::Or(left, right) => {
+                self.expr(location.clone(), left);
+                self.expr(location, right);
+            }
+            Expr::Bool(_) | Expr::Flag(_) | Expr::Count(_, _, _) => {}
+        }
+    }
+
+    fn script(&mut self, location: Location, script: &Script) {
+        for statement in script.0.iter() {
+            match statement {
+                Statement::Give(entity) | Statement::Remove(entity) | Statement::Place(entity) => {
+                    self.entity(location.clone(), *entity)
+                }
+                Statement::Move(room) | Statement::Describe(room, _) | Statement::Exit(_, room) => {
+                    self.room(location.clone(), *room)
+                }
+                Statement::Activate(event) | Statement::Deactivate(event) => {
+                    self.event(location.clone(), *event)
+                }
+                Statement::If(condition, then, otherwise) => {
+                    self.expr(location.clone(), condition);
+                    self.script(location.clone(), then);
+                    self.script(location.clone(), otherwise);
+                }
+                _ => {}
+            }
+        }
+    }
+
+    fn check_events(&mut self) {
+        for (event_id, event) in self.world.events.iter().enumerate() {
+            let event_id = EventId(event_id);
+            self.condition(Location::Event(event_id), &event.condition);
+            for (index, command) in event.command_stack.iter().enumerate() {
+                self.command(Location::EventCommand(event_id, index), command);
+            }
+            self.script(Location::EventScript(event_id), &event.script);
+        }
+        let mut active_events: Vec<EventId> = self.world.active_events.iter().copied().collect();
+        active_events.sort();
+        for event in active_events {
+            self.event(Location::ActiveEvents, event);
+        }
+    }
+
+    fn check_aliases(&mut self) {
+        let mut owners: HashMap<&str, Vec<String>> = HashMap::new();
+        for (id, entity) in self.world.entities.iter() {
+            for alias in entity.aliases.iter() {
+                owners
+                    .entry(alias)
+                    .or_default()
+                    .push(format!("entity {id} ({})", entity.name));
+            }
+        }
+        for (id, actor) in self.world.actors.iter() {
+            for alias in actor.aliases.iter() {
+                owners
+                    .entry(alias)
+                    .or_default()
+                    .push(format!("actor {id} ({})", actor.name));
+            }
+        }
+        let mut collisions: Vec<(&str, Vec<String>)> = owners
+            .into_iter()
+            .filter(|(_, owners)| owners.len() > 1)
+            .collect();
+        collisions.sort();
+        for (alias, mut owners) in collisions {
+            owners.sort();
+            self.diagnostics.push(Diagnostic::warning(
+                Location::Aliases,
+                Problem::AliasCollision(alias.to_string(), owners),
+            ));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn default_world_has_no_errors() {
+        let diagnostics = validate(&World::default());
+        assert!(!diagnostics.iter().any(|d| d.is_error()));
+    }
+
+    #[test]
+    fn finds_dangling_references() {
+        let mut world = World {
+            start: RoomId(42),
+            ..Default::default()
+        };
+        world.events[1].condition = Condition::All(vec![
+            Condition::Location(RoomId(2)),
+            Condition::Not(Box::new(Condition::Actor(ActorId(7)))),
+        ]);
+        world.events[0]
+            .command_stack
+            .push(Command::AddItemToRoom(EntityId(99)));
+        let diagnostics = validate(&world);
+        assert!(diagnostics.contains(&Diagnostic::error(
+            Location::Start,
+            Problem::MissingRoom(RoomId(42))
+        )));
+        assert!(diagnostics.contains(&Diagnostic::error(
+            Location::EventCommand(EventId(0), 3),
+            Problem::MissingEntity(EntityId(99))
+        )));
+        assert!(diagnostics.contains(&Diagnostic::error(
+            Location::Event(EventId(1)),
+            Problem::MissingActor(ActorId(7))
+        )));
+    }
+}
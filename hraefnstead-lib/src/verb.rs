@@ -0,0 +1,298 @@
+//! The verbs the parser understands.
+//!
+//! Every verb has a name, the words standing for it, the arguments it
+//! expects and a help text. The verbs themselves are built in, their words
+//! and help come from the catalog of a [`Locale`] and worlds can give them
+//! more synonyms, e.g. `"synonyms": {"take": ["grab", "get"]}`. Besides the
+//! verbs a vocabulary knows the words for directions, the names of the
+//! world's named exits and the small words of the locale's grammar, like
+//! "then", "all but" or "give ... to".
+
+use crate::direction::Direction;
+use crate::locale::{Catalog, Locale};
+
+/// What a verb expects after it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Arguments {
+    /// Nothing, anything after the verb is ignored.
+    Nothing,
+    /// An optional file name, kept as typed.
+    FileName,
+    /// A direction.
+    Direction,
+    /// An object.
+    Object,
+    /// An object, or two joined by a preposition.
+    Objects,
+    /// Two objects joined by a preposition.
+    TwoObjects,
+    /// An optional verb to explain.
+    Topic,
+}
+
+/// The message ids for a missing object and a missing indirect object.
+type Missing = (&'static str, &'static str);
+
+const MISSING_OBJECT: Missing = ("missing_object", "missing_object");
+
+/// The built in verbs with the arguments they expect and the messages shown
+/// when their objects are missing.
+const VERBS: [(&str, Arguments, Missing); 34] = [
+    ("look", Arguments::Nothing, MISSING_OBJECT),
+    ("quit", Arguments::Nothing, MISSING_OBJECT),
+    ("save", Arguments::FileName, MISSING_OBJECT),
+    ("load", Arguments::FileName, MISSING_OBJECT),
+    ("go", Arguments::Direction, MISSING_OBJECT),
+    ("north", Arguments::Nothing, MISSING_OBJECT),
+    ("south", Arguments::Nothing, MISSING_OBJECT),
+    ("east", Arguments::Nothing, MISSING_OBJECT),
+    ("west", Arguments::Nothing, MISSING_OBJECT),
+    ("northeast", Arguments::Nothing, MISSING_OBJECT),
+    ("northwest", Arguments::Nothing, MISSING_OBJECT),
+    ("southeast", Arguments::Nothing, MISSING_OBJECT),
+    ("southwest", Arguments::Nothing, MISSING_OBJECT),
+    ("up", Arguments::Nothing, MISSING_OBJECT),
+    ("down", Arguments::Nothing, MISSING_OBJECT),
+    ("in", Arguments::Nothing, MISSING_OBJECT),
+    ("out", Arguments::Nothing, MISSING_OBJECT),
+    ("take", Arguments::Object, MISSING_OBJECT),
+    ("drop", Arguments::Object, MISSING_OBJECT),
+    ("inventory", Arguments::Nothing, MISSING_OBJECT),
+    ("examine", Arguments::Object, MISSING_OBJECT),
+    ("use", Arguments::Objects, MISSING_OBJECT),
+    (
+        "attack",
+        Arguments::Objects,
+        ("missing_object_attack", "missing_object_attack"),
+    ),
+    (
+        "give",
+        Arguments::TwoObjects,
+        ("missing_object_give", "missing_object_give"),
+    ),
+    (
+        "put",
+        Arguments::TwoObjects,
+        ("missing_object_put", "missing_object_put"),
+    ),
+    ("open", Arguments::Object, MISSING_OBJECT),
+    ("close", Arguments::Object, MISSING_OBJECT),
+    ("lock", Arguments::Objects, MISSING_OBJECT),
+    ("unlock", Arguments::Objects, MISSING_OBJECT),
+    (
+        "craft",
+        Arguments::Object,
+        ("missing_object_craft", "missing_object_craft"),
+    ),
+    ("map", Arguments::Nothing, MISSING_OBJECT),
+    ("again", Arguments::Nothing, MISSING_OBJECT),
+    ("history", Arguments::Nothing, MISSING_OBJECT),
+    ("help", Arguments::Topic, MISSING_OBJECT),
+];
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Verb {
+    pub name: String,
+    /// The words standing for the verb, the first one is shown in the help.
+    pub words: Vec<String>,
+    pub arguments: Arguments,
+    pub help: String,
+    /// The message shown when the object is missing, e.g. "take".
+    pub missing_object: String,
+    /// The message shown when the indirect object is missing, e.g. "give coin".
+    pub missing_indirect_object: String,
+}
+
+impl Verb {
+    /// Whether the word stands for the verb.
+    pub fn is_called(&self, word: &str) -> bool {
+        self.words.iter().any(|known| known == word)
+    }
+}
+
+/// All verbs of a world in one locale, with the other words the parser
+/// needs.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Vocabulary {
+    locale: Locale,
+    verbs: Vec<Verb>,
+    /// The names of the named exits, like "door".
+    exits: Vec<String>,
+}
+
+impl Default for Vocabulary {
+    fn default() -> Self {
+        Self::new(Locale::default())
+    }
+}
+
+impl Vocabulary {
+    /// The verbs with the words and help texts of the locale.
+    pub fn new(locale: Locale) -> Self {
+        let verbs = VERBS
+            .iter()
+            .map(
+                |(name, arguments, (missing_object, missing_indirect_object))| {
+                    let text = [locale, Locale::En]
+                        .into_iter()
+                        .find_map(|locale| locale.catalog().verbs.get(*name));
+                    Verb {
+                        name: name.to_string(),
+                        words: text
+                            .map(|text| text.words.clone())
+                            .filter(|words| !words.is_empty())
+                            .unwrap_or_else(|| vec![name.to_string()]),
+                        arguments: *arguments,
+                        help: text.map(|text| text.help.clone()).unwrap_or_default(),
+                        missing_object: missing_object.to_string(),
+                        missing_indirect_object: missing_indirect_object.to_string(),
+                    }
+                },
+            )
+            .collect();
+        Self {
+            locale,
+            verbs,
+            exits: Vec::new(),
+        }
+    }
+
+    pub fn locale(&self) -> Locale {
+        self.locale
+    }
+
+    pub fn verbs(&self) -> &[Verb] {
+        &self.verbs
+    }
+
+    /// The verb the word stands for.
+    pub fn find(&self, word: &str) -> Option<&Verb> {
+        self.verbs.iter().find(|verb| verb.is_called(word))
+    }
+
+    /// All words standing for verbs.
+    pub fn words(&self) -> impl Iterator<Item = &str> {
+        self.verbs
+            .iter()
+            .flat_map(|verb| verb.words.iter().map(String::as_str))
+    }
+
+    /// Let the word stand for the verb. Returns false if there is no such
+    /// verb.
+    pub fn add_synonym(&mut self, verb: &str, synonym: &str) -> bool {
+        match self.verbs.iter_mut().find(|known| known.name == verb) {
+            Some(verb) => {
+                verb.words.push(synonym.to_lowercase());
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// The help text of a verb, or the list of all verbs for any other topic.
+    pub fn help(&self, topic: &str) -> String {
+        if let Some(verb) = self.find(topic) {
+            return verb.help.clone();
+        }
+        let lines: Vec<String> = self
+            .verbs
+            .iter()
+            .filter_map(|verb| match verb.words.split_first()? {
+                (word, []) => Some(word.clone()),
+                (word, synonyms) => Some(format!("{word} ({})", synonyms.join(", "))),
+            })
+            .collect();
+        lines.join("\n")
+    }
+
+    /// Let the name stand for a named exit.
+    pub fn add_exit(&mut self, name: &str) {
+        let name = name.to_lowercase();
+        if !self.exits.contains(&name) {
+            self.exits.push(name);
+        }
+    }
+
+    /// The direction or named exit the words stand for.
+    pub fn direction(&self, words: &str) -> Option<Direction> {
+        Direction::ALL
+            .into_iter()
+            .find(|direction| {
+                self.grammar(|catalog| catalog.directions.get(&direction.to_string()))
+                    .iter()
+                    .any(|known| known == words)
+            })
+            .or_else(|| {
+                self.exits
+                    .contains(&words.to_string())
+                    .then(|| Direction::Named(words.to_string()))
+            })
+    }
+
+    /// Words without meaning for the game, like articles.
+    pub fn fillers(&self) -> &'static [String] {
+        self.grammar(|catalog| Some(&catalog.fillers))
+    }
+
+    /// Words separating commands, like "then".
+    pub fn separators(&self) -> &'static [String] {
+        self.grammar(|catalog| Some(&catalog.separators))
+    }
+
+    /// Words for every object, like "all".
+    pub fn quantifiers(&self) -> &'static [String] {
+        self.grammar(|catalog| Some(&catalog.quantifiers))
+    }
+
+    /// Words starting the exceptions of a quantifier, like "but".
+    pub fn exceptions(&self) -> &'static [String] {
+        self.grammar(|catalog| Some(&catalog.exceptions))
+    }
+
+    /// Words joining objects, like "and".
+    pub fn conjunctions(&self) -> &'static [String] {
+        self.grammar(|catalog| Some(&catalog.conjunctions))
+    }
+
+    /// The prepositions joining the two objects of a verb, like "to" in
+    /// "give coin to goblin".
+    pub fn prepositions(&self, verb: &str) -> &'static [String] {
+        self.grammar(|catalog| catalog.prepositions.get(verb))
+    }
+
+    /// The words of a pronoun: "it" for a thing, "him" for an actor and
+    /// "them" for all objects.
+    pub fn pronouns(&self, pronoun: &str) -> &'static [String] {
+        self.grammar(|catalog| catalog.pronouns.get(pronoun))
+    }
+
+    /// Words of the locale's catalog, or of the English one if it has none.
+    fn grammar(
+        &self,
+        pick: impl Fn(&'static Catalog) -> Option<&'static Vec<String>>,
+    ) -> &'static [String] {
+        [self.locale, Locale::En]
+            .into_iter()
+            .filter_map(|locale| pick(locale.catalog()))
+            .find(|words| !words.is_empty())
+            .map_or(&[], Vec::as_slice)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn synonyms_extend_verbs() {
+        let mut vocabulary = Vocabulary::default();
+        assert!(vocabulary.add_synonym("take", "Grab"));
+        assert!(!vocabulary.add_synonym("dance", "boogie"));
+        assert_eq!(
+            vocabulary.find("grab").map(|verb| verb.name.as_str()),
+            Some("take")
+        );
+        assert_eq!(vocabulary.help("grab"), vocabulary.help("take"));
+        assert!(vocabulary.help("Default").contains("\ntake (t, grab)\n"));
+    }
+}
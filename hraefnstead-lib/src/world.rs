@@ -0,0 +1,189 @@
+//! World definitions loaded from data files.
+//!
+//! A world is a JSON document with the following top level fields:
+//!
+//! * `id`: identifier of the world, saves can only be loaded into the world
+//!   with the same id
+//! * `version`: version of the world content
+//! * `start`: key of the room the player starts in
+//! * `rooms`: list of rooms, each with a `key`, `name`, `description`, the
+//!   keys of the `entities` and `actors` initially placed in it and the map of
+//!   `exits` from a direction (`"North"`, `"South"`, `"East"`, `"West"`, the
+//!   diagonals like `"NorthEast"`, `"Up"`, `"Down"`, `"In"` and `"Out"`) or the
+//!   name of an exit like `"door"` to the key of a room
+//! * `entities`: list of entities with `key`, `name`, `description` and `aliases`
+//! * `actors`: list of actors with `key`, `name`, `description` and `aliases`
+//! * `doors`: optional list of doors with `key`, `name` and `aliases`, the
+//!   `sides` mapping the keys of the two rooms to the exit the door stands
+//!   in, e.g. `{"hall": "North", "vault": "South"}`, its `state` at the start
+//!   (`"Open"`, the default, `"Closed"` or `"Locked"`) and the key of the
+//!   entity fitting its `lock`; a door adds its exits to both rooms
+//! * `craft_recipes`: map from the key of an ingredient to the key of the
+//!   crafted item
+//! * `conditions`: list of named conditions with a `key` and the `condition`,
+//!   for conditions shared by several events
+//! * `events`: list of events with a `key`, the triggering `condition`, the
+//!   `message` shown, the `command_stack` executed and an optional `script`
+//!   run afterwards; commands refer to entities, actors, rooms and events by
+//!   key, e.g. `{"AddItemToRoom": "copper_coin"}`
+//! * `active_events`: keys of the events that are active at the start
+//! * `synonyms`: optional map from the name of a verb to more words for it,
+//!   e.g. `{"take": ["grab"]}`, see [`crate::verb`]
+//! * `locale`: optional language of the engine's messages and the parser,
+//!   `"en"` (the default) or `"de"`, see [`crate::locale`]
+//!
+//! Conditions are expression trees built from the tests `{"CommandIs": command}`,
+//! `{"Location": room}`, `{"ObjectInInventory": entity}`, `{"Actor": actor}`
+//! and `{"ActorHere": actor}`, which only holds while the actor is in the
+//! player's room, combined with `{"All": [...]}`, `{"Any": [...]}` and
+//! `{"Not": condition}`.
+//! A plain string like `"examine_bed_in_chamber"` refers to a named condition,
+//! `{"Script": "flag lamp_lit and not has key"}` is a condition in the event
+//! script language described in [`crate::script`].
+//! The older two-argument forms `And`, `Or`, `NotAnd` and `NotOr` as well as
+//! `NotCommandIs`, `NotLocation` and `NotObjectInInventory` are still accepted,
+//! their conditions may also be given by their index in `conditions`.
+//!
+//! The dungeon shipped with the game is bundled as [`World::default`], see
+//! `worlds/hraefnstead.json` for a complete example.
+
+use crate::actor::Actor;
+use crate::command::Command;
+use crate::direction::Direction;
+use crate::door::Door;
+use crate::dot::to_dot;
+use crate::entity::Entity;
+use crate::event::Event;
+use crate::id::{ActorId, EntityId, EventId, RoomId};
+use crate::locale::Locale;
+use crate::room::Room;
+use crate::source::{KeyError, WorldSource};
+use crate::validate::{validate, Diagnostic};
+use crate::verb::Vocabulary;
+
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::path::Path;
+use std::str::FromStr;
+
+use thiserror::Error;
+
+/// The world file of the default dungeon.
+pub const DEFAULT_WORLD: &str = include_str!("../worlds/hraefnstead.json");
+
+#[derive(Debug, Error)]
+pub enum WorldError {
+    #[error("could not read world file: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("invalid world definition: {0}")]
+    Parse(#[from] serde_json::Error),
+    #[error("invalid references in world definition:\n{}", format_key_errors(.0))]
+    Keys(Vec<KeyError>),
+}
+
+fn format_key_errors(errors: &[KeyError]) -> String {
+    let errors: Vec<String> = errors.iter().map(|e| format!("  {e}")).collect();
+    errors.join("\n")
+}
+
+#[derive(Debug, Clone)]
+pub struct World {
+    pub id: String,
+    pub version: u32,
+    pub start: RoomId,
+    pub rooms: Vec<Room>,
+    pub entities: HashMap<EntityId, Entity>,
+    pub actors: HashMap<ActorId, Actor>,
+    pub doors: Vec<Door>,
+    pub craft_recipes: HashMap<EntityId, EntityId>,
+    pub events: Vec<Event>,
+    pub active_events: HashSet<EventId>,
+    /// More words for verbs, by the name of the verb.
+    pub synonyms: BTreeMap<String, Vec<String>>,
+    /// The locale games in the world start with.
+    pub locale: Locale,
+}
+
+impl Default for World {
+    fn default() -> Self {
+        DEFAULT_WORLD
+            .parse()
+            .expect("bundled default world must be valid")
+    }
+}
+
+impl FromStr for World {
+    type Err = WorldError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        serde_json::from_str::<WorldSource>(s)?.resolve()
+    }
+}
+
+impl World {
+    /// Read a world definition from a JSON file.
+    pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Self, WorldError> {
+        std::fs::read_to_string(path)?.parse()
+    }
+
+    /// Report dangling references and other inconsistencies, see [`validate`].
+    pub fn validate(&self) -> Vec<Diagnostic> {
+        validate(self)
+    }
+
+    /// The verbs of the locale with the synonyms and named exits of the world.
+    pub fn vocabulary(&self, locale: Locale) -> Vocabulary {
+        let mut vocabulary = Vocabulary::new(locale);
+        for (verb, synonyms) in self.synonyms.iter() {
+            for synonym in synonyms {
+                vocabulary.add_synonym(verb, synonym);
+            }
+        }
+        for name in self.named_exits() {
+            vocabulary.add_exit(&name);
+        }
+        vocabulary
+    }
+
+    /// The names of the named exits of the rooms and of those events open.
+    pub fn named_exits(&self) -> BTreeSet<String> {
+        let rooms = self.rooms.iter().flat_map(|room| room.get_exits().keys());
+        let events = self.events.iter().flat_map(|event| {
+            event
+                .command_stack
+                .iter()
+                .filter_map(|command| match command {
+                    Command::AddExit(direction, _) => Some(direction),
+                    _ => None,
+                })
+                .chain(event.script.exits())
+        });
+        rooms
+            .chain(events)
+            .filter_map(|direction| match direction {
+                Direction::Named(name) => Some(name.clone()),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// The room graph, and with `events` the event graph, in DOT, see [`to_dot`].
+    pub fn to_dot(&self, events: bool) -> String {
+        to_dot(self, events)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn default_world_resolves_keys() {
+        let world = World::default();
+        assert_eq!(world.start, RoomId(0));
+        assert_eq!(world.rooms.len(), 4);
+        assert_eq!(world.entities[&EntityId(2)].name, "A copper coin");
+        assert_eq!(world.actors[&ActorId(1)].name, "Goblin");
+        assert_eq!(world.craft_recipes[&EntityId(1)], EntityId(5));
+        assert!(world.active_events.contains(&EventId(5)));
+    }
+}